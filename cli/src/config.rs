@@ -0,0 +1,163 @@
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    // rpc url, or one of "localnet", "devnet", "mainnet"
+    pub cluster: String,
+    pub keypair: String,
+    pub program_id: Option<String>,
+    pub treasury: TreasuryConfig,
+    #[serde(default)]
+    pub epoch: EpochConfig,
+    #[serde(default)]
+    pub bonds: BTreeMap<String, BondConfig>,
+    // recipients of fees accrued by the fee split, left as is when empty
+    #[serde(default)]
+    pub fee_split: Vec<FeeSplitConfig>,
+    // token metadata of the reserve and staking mints, left as is when unset
+    pub metadata: Option<MetadataConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TreasuryConfig {
+    // base key the treasury pda is derived from
+    pub key: String,
+    pub dao: String,
+    pub authority: Option<String>,
+    // 1e9 percent of reserve supply to distribute to stakers per epoch
+    pub staking_rate: u64,
+    #[serde(default)]
+    pub staking_warmup: u64,
+    #[serde(default)]
+    pub staking_cooldown: u64,
+    // seconds, 0 disables locks
+    #[serde(default)]
+    pub lock_max_duration: u64,
+    // 1e9 percent of each rebase going to locks
+    #[serde(default)]
+    pub lock_boost_rate: u64,
+    // lock voting weight needed for proposals to pass, 0 disables governance
+    #[serde(default)]
+    pub governance_quorum: u64,
+    // 1e9 percent of votes cast in favor needed
+    #[serde(default)]
+    pub governance_threshold: u64,
+    // 1e9 percent of quote tokens taken on third party bond markets
+    #[serde(default)]
+    pub market_fee: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct EpochConfig {
+    // seconds
+    pub length: u64,
+    // staking_rate moves by adjustment_rate per epoch toward adjustment_target
+    pub adjustment_rate: u64,
+    pub adjustment_target: u64,
+    // rfv of max_debt split across bonds by gauge votes, 0 keeps each bond's
+    // configured max_debt
+    pub gauge_budget: u64,
+}
+
+impl Default for EpochConfig {
+    fn default() -> EpochConfig {
+        EpochConfig {
+            length: 8 * 60 * 60,
+            adjustment_rate: 0,
+            adjustment_target: 0,
+            gauge_budget: 0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BondConfig {
+    pub mint: String,
+    pub vesting_period: u64,
+    pub rfv_rate: u64,
+    pub min_price: u64,
+    pub max_payout: u64,
+    pub max_debt: u64,
+    pub fee: u64,
+    pub bcv: u64,
+    // max rfv per wallet per vesting_period, and max payout per slot
+    #[serde(default)]
+    pub max_debt_user: u64,
+    #[serde(default)]
+    pub max_payout_slot: u64,
+    // 1e9 percent of unvested bond payouts forfeited on early exit, 0
+    // disables early exits
+    #[serde(default)]
+    pub exit_penalty: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeeSplitConfig {
+    pub owner: String,
+    // basis points, adding up to 10_000
+    pub weight: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetadataConfig {
+    pub reserve: TokenMetadataConfig,
+    pub staking: TokenMetadataConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenMetadataConfig {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("reading config {}", path.display()))?;
+        toml::from_str(&data).with_context(|| format!("parsing config {}", path.display()))
+    }
+
+    pub fn rpc_url(&self) -> String {
+        match self.cluster.as_str() {
+            "localnet" => "http://127.0.0.1:8899".to_string(),
+            "devnet" => "https://api.devnet.solana.com".to_string(),
+            "mainnet" => "https://api.mainnet-beta.solana.com".to_string(),
+            url => url.to_string(),
+        }
+    }
+
+    pub fn keypair_path(&self) -> PathBuf {
+        expand_home(&self.keypair)
+    }
+
+    pub fn program_id(&self) -> Result<Pubkey> {
+        match &self.program_id {
+            Some(key) => pubkey(key),
+            None => Ok(reserve::ID),
+        }
+    }
+
+    pub fn bond(&self, name: &str) -> Result<&BondConfig> {
+        self.bonds
+            .get(name)
+            .ok_or_else(|| anyhow!("no bond named {:?} in config", name))
+    }
+}
+
+pub fn pubkey(s: &str) -> Result<Pubkey> {
+    Pubkey::from_str(s).map_err(|_| anyhow!("invalid pubkey {:?}", s))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
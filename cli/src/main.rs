@@ -0,0 +1,1412 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::token::{self, Mint, TokenAccount};
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, Subcommand};
+use config::{pubkey, Config};
+use reserve::math::{
+    bond_quote, protocol_metrics, staking_shares, vested_amount, VestingSchedule, ONE,
+    VESTING_CLIFF, VESTING_EXPONENTIAL, VESTING_LINEAR, VESTING_MONTHLY,
+};
+use reserve::{
+    Bond, BondSchedule, Epoch, EpochIndex, FeeSplit, LockCurve, Multisig, MultisigAccount,
+    MultisigTransaction, Oracle, Treasury, User, ORACLE_FEED, ORACLE_POOL, ROLE_BOND_MANAGER,
+    ROLE_GUARDIAN, ROLE_RISK_MANAGER, ROLE_TREASURER,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::hashv;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use solana_sdk::{system_program, sysvar};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod config;
+
+#[derive(Parser)]
+#[clap(name = "reserve-cli", about = "Administer a reserve protocol treasury")]
+struct Opts {
+    #[clap(short, long, default_value = "reserve.toml")]
+    config: PathBuf,
+    /// Print the transaction instead of sending it
+    #[clap(long)]
+    dry_run: bool,
+    /// Propose admin instructions to the treasury multisig instead of
+    /// signing them as the authority
+    #[clap(long)]
+    multisig: bool,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the treasury, its mints, its epoch and the dao reserve token account
+    InitTreasury,
+    /// Apply the [treasury] and [epoch] sections of the config
+    ConfigureTreasury,
+    /// Create a bond market and the treasury token account for its mint
+    CreateBond { name: String },
+    /// Apply the [bonds.<name>] section of the config
+    ConfigureBond { name: String },
+    /// Print on-chain state
+    #[clap(subcommand)]
+    Show(Show),
+    /// Quote a bond deposit of `amount` (in the bond token's smallest unit)
+    Quote { name: String, amount: u64 },
+    /// End the current epoch, minting its staking rewards
+    CrankRebase,
+    /// Record the current price of every configured bond into its price
+    /// history, creating it if needed
+    CrankObserve,
+    /// Grant roles (bond-manager, risk-manager, treasurer, guardian or all) to a wallet
+    GrantRole { wallet: String, roles: Vec<String> },
+    /// Revoke roles from a wallet
+    RevokeRole { wallet: String, roles: Vec<String> },
+    /// Pause deposits into a bond, or resume them
+    PauseBond {
+        name: String,
+        #[clap(long)]
+        resume: bool,
+    },
+    /// Set how new deposits into a bond vest (linear, cliff, monthly or
+    /// exponential), `cliff` being in seconds
+    ScheduleBond {
+        name: String,
+        kind: String,
+        #[clap(long, default_value = "0")]
+        cliff: u64,
+    },
+    /// Only let wallets listed in a csv file of `wallet,max_amount` lines
+    /// deposit into a bond (printing their proofs), or anyone without one
+    AllowlistBond { name: String, path: Option<PathBuf> },
+    /// Pay out the fees accrued in the fee split's token account for `mint`
+    DistributeFees { mint: String },
+    /// Read the reserve token's market price from a constant product pool
+    /// (its reserve and quote token accounts) or a price feed, bonds above
+    /// it or below backing are then rejected
+    SetOracle {
+        /// pool or feed
+        kind: String,
+        source: String,
+        source_quote: Option<String>,
+        #[clap(long, default_value = "6")]
+        quote_decimals: u64,
+        /// seconds a price can be used for
+        #[clap(long, default_value = "60")]
+        max_age: u64,
+        /// seconds pool prices are averaged over
+        #[clap(long, default_value = "30")]
+        window: u64,
+        /// min seconds between pool reads
+        #[clap(long, default_value = "10")]
+        interval: u64,
+    },
+    /// Set the max fee (1e9 percent of bond fees) a referrer wallet can charge
+    ApproveReferrer { wallet: String, fee_max: u64 },
+    /// Move the treasury and configured bonds created by an older program
+    /// version to the current account layouts
+    Migrate,
+    /// Create the treasury multisig (make its signer the authority to use it)
+    CreateMultisig {
+        owners: Vec<String>,
+        #[clap(long)]
+        threshold: u64,
+    },
+    /// Approve a multisig transaction
+    ApproveTransaction { transaction: String },
+    /// Execute an approved multisig transaction
+    ExecuteTransaction { transaction: String },
+}
+
+#[derive(Subcommand)]
+enum Show {
+    Treasury,
+    Bond { name: String },
+    User { wallet: Option<String> },
+    Multisig,
+}
+
+struct Cli {
+    config: Config,
+    rpc: RpcClient,
+    payer: Keypair,
+    program_id: Pubkey,
+    dry_run: bool,
+    use_multisig: bool,
+}
+
+fn main() -> Result<()> {
+    let opts = Opts::parse();
+    let config = Config::load(&opts.config)?;
+    let payer = read_keypair_file(config.keypair_path())
+        .map_err(|e| anyhow!("reading keypair {}: {}", config.keypair, e))?;
+    let cli = Cli {
+        rpc: RpcClient::new_with_commitment(config.rpc_url(), CommitmentConfig::confirmed()),
+        program_id: config.program_id()?,
+        dry_run: opts.dry_run,
+        use_multisig: opts.multisig,
+        payer,
+        config,
+    };
+
+    match opts.command {
+        Command::InitTreasury => cli.init_treasury(),
+        Command::ConfigureTreasury => cli.configure_treasury(),
+        Command::CreateBond { name } => cli.create_bond(&name),
+        Command::ConfigureBond { name } => cli.configure_bond(&name),
+        Command::Show(Show::Treasury) => cli.show_treasury(),
+        Command::Show(Show::Bond { name }) => cli.show_bond(&name),
+        Command::Show(Show::User { wallet }) => cli.show_user(wallet),
+        Command::Quote { name, amount } => cli.quote(&name, amount),
+        Command::CrankRebase => cli.crank_rebase(),
+        Command::CrankObserve => cli.crank_observe(),
+        Command::GrantRole { wallet, roles } => cli.grant_role(&wallet, &roles),
+        Command::RevokeRole { wallet, roles } => cli.revoke_role(&wallet, &roles),
+        Command::PauseBond { name, resume } => cli.pause_bond(&name, !resume),
+        Command::ScheduleBond { name, kind, cliff } => cli.schedule_bond(&name, &kind, cliff),
+        Command::AllowlistBond { name, path } => cli.allowlist_bond(&name, path),
+        Command::DistributeFees { mint } => cli.distribute_fees(&mint),
+        Command::SetOracle {
+            kind,
+            source,
+            source_quote,
+            quote_decimals,
+            max_age,
+            window,
+            interval,
+        } => cli.set_oracle(
+            &kind,
+            &source,
+            source_quote,
+            quote_decimals,
+            max_age,
+            window,
+            interval,
+        ),
+        Command::ApproveReferrer { wallet, fee_max } => cli.approve_referrer(&wallet, fee_max),
+        Command::Migrate => cli.migrate(),
+        Command::CreateMultisig { owners, threshold } => cli.create_multisig(&owners, threshold),
+        Command::ApproveTransaction { transaction } => cli.approve_transaction(&transaction),
+        Command::ExecuteTransaction { transaction } => cli.execute_transaction(&transaction),
+        Command::Show(Show::Multisig) => cli.show_multisig(),
+    }
+}
+
+impl Cli {
+    fn init_treasury(&self) -> Result<()> {
+        let key = pubkey(&self.config.treasury.key)?;
+        let dao = pubkey(&self.config.treasury.dao)?;
+        let (treasury, bump) = self.pda(&[b"treasury", key.as_ref()]);
+        let (mint_reserve, mint_reserve_bump) = self.pda(&[b"treasury_mint_reserve"]);
+        let (mint_staking, mint_staking_bump) = self.pda(&[b"treasury_mint_staking"]);
+        let (token_reserve_staking, token_reserve_staking_bump) =
+            self.pda(&[b"treasury_token_reserve_staking"]);
+        let (token_staking_vesting, token_staking_vesting_bump) =
+            self.pda(&[b"treasury_token_staking_vesting"]);
+
+        let mut ixs = vec![self.ix(
+            reserve::accounts::Initialize {
+                signer: self.payer.pubkey(),
+                treasury,
+                mint_reserve,
+                mint_staking,
+                token_reserve_staking,
+                token_staking_vesting,
+                dao,
+                rent: sysvar::rent::ID,
+                token_program: token::ID,
+                system_program: system_program::ID,
+            },
+            reserve::instruction::Initialize {
+                key,
+                bump,
+                _mint_reserve_bump: mint_reserve_bump,
+                _mint_staking_bump: mint_staking_bump,
+                _token_reserve_staking_bump: token_reserve_staking_bump,
+                _token_staking_vesting_bump: token_staking_vesting_bump,
+            },
+        )];
+        let (epoch, epoch_bump) = self.pda(&[b"epoch", treasury.as_ref()]);
+        ixs.push(self.ix(
+            reserve::accounts::EpochInitialize {
+                signer: self.payer.pubkey(),
+                treasury,
+                epoch,
+                system_program: system_program::ID,
+            },
+            reserve::instruction::EpochInitialize {
+                bump: epoch_bump,
+                length: self.config.epoch.length,
+                end: unix_now()? + self.config.epoch.length,
+            },
+        ));
+        // the deployer starts with every role
+        let (role, role_bump) = self.role(&self.payer.pubkey());
+        ixs.push(self.ix(
+            reserve::accounts::RoleInitialize {
+                signer: self.payer.pubkey(),
+                treasury,
+                member: self.payer.pubkey(),
+                role,
+                system_program: system_program::ID,
+            },
+            reserve::instruction::RoleInitialize { bump: role_bump },
+        ));
+        ixs.push(self.ix(
+            reserve::accounts::RoleConfigure {
+                signer: self.payer.pubkey(),
+                treasury,
+                role,
+            },
+            reserve::instruction::RoleGrant {
+                roles: parse_roles(&["all".to_string()])?,
+            },
+        ));
+        let (market_config, market_config_bump) = self.pda(&[b"market_config", treasury.as_ref()]);
+        ixs.push(self.ix(
+            reserve::accounts::MarketConfigInitialize {
+                signer: self.payer.pubkey(),
+                treasury,
+                market_config,
+                system_program: system_program::ID,
+            },
+            reserve::instruction::MarketConfigInitialize {
+                bump: market_config_bump,
+            },
+        ));
+        let (fee_split, fee_split_bump) = self.pda(&[b"fee_split", treasury.as_ref()]);
+        ixs.push(self.ix(
+            reserve::accounts::FeeSplitInitialize {
+                signer: self.payer.pubkey(),
+                treasury,
+                fee_split,
+                system_program: system_program::ID,
+            },
+            reserve::instruction::FeeSplitInitialize {
+                bump: fee_split_bump,
+            },
+        ));
+        ixs.extend(self.create_token_account(&dao, &mint_reserve)?);
+        self.send(ixs)?;
+        println!("treasury {}", treasury);
+        println!("mint_reserve {}", mint_reserve);
+        println!("mint_staking {}", mint_staking);
+        Ok(())
+    }
+
+    fn configure_treasury(&self) -> Result<()> {
+        let c = &self.config.treasury;
+        let authority = match &c.authority {
+            Some(authority) => pubkey(authority)?,
+            None => self.authority(),
+        };
+        let treasury = self.treasury();
+        let mut ixs = vec![
+            self.ix(
+                reserve::accounts::TreasuryConfigure {
+                    signer: self.authority(),
+                    treasury,
+                },
+                reserve::instruction::TreasuryConfigure {
+                    dao: pubkey(&c.dao)?,
+                    authority,
+                    staking_rate: c.staking_rate,
+                    staking_warmup: c.staking_warmup,
+                    staking_cooldown: c.staking_cooldown,
+                    lock_max_duration: c.lock_max_duration,
+                    lock_boost_rate: c.lock_boost_rate,
+                },
+            ),
+            self.ix(
+                reserve::accounts::EpochConfigure {
+                    signer: self.authority(),
+                    treasury,
+                    epoch: self.epoch(),
+                },
+                reserve::instruction::EpochConfigure {
+                    length: self.config.epoch.length,
+                    adjustment_rate: self.config.epoch.adjustment_rate,
+                    adjustment_target: self.config.epoch.adjustment_target,
+                    gauge_budget: self.config.epoch.gauge_budget,
+                },
+            ),
+            self.ix(
+                reserve::accounts::GovernanceConfigure {
+                    signer: self.authority(),
+                    treasury,
+                },
+                reserve::instruction::GovernanceConfigure {
+                    quorum: c.governance_quorum,
+                    threshold: c.governance_threshold,
+                },
+            ),
+            self.ix(
+                reserve::accounts::MarketConfigConfigure {
+                    signer: self.authority(),
+                    treasury,
+                    market_config: self.pda(&[b"market_config", treasury.as_ref()]).0,
+                },
+                reserve::instruction::MarketConfigConfigure { fee: c.market_fee },
+            ),
+        ];
+        // sized from lock_max_duration, so only once locks are enabled
+        let (lock_curve, lock_curve_bump) = self.pda(&[b"lock_curve", treasury.as_ref()]);
+        if c.lock_max_duration > 0 && self.rpc.get_account(&lock_curve).is_err() {
+            ixs.push(self.ix(
+                reserve::accounts::LockCurveInitialize {
+                    signer: self.authority(),
+                    treasury,
+                    lock_curve,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::LockCurveInitialize {
+                    bump: lock_curve_bump,
+                },
+            ));
+        }
+        if !self.config.fee_split.is_empty() {
+            ixs.push(
+                self.ix(
+                    reserve::accounts::FeeSplitConfigure {
+                        signer: self.authority(),
+                        treasury,
+                        fee_split: self.fee_split(),
+                    },
+                    reserve::instruction::FeeSplitConfigure {
+                        owners: self
+                            .config
+                            .fee_split
+                            .iter()
+                            .map(|r| pubkey(&r.owner))
+                            .collect::<Result<_>>()?,
+                        weights: self.config.fee_split.iter().map(|r| r.weight).collect(),
+                    },
+                ),
+            );
+        }
+        self.send(ixs)?;
+
+        // in its own transaction as uris make these large
+        if let Some(m) = &self.config.metadata {
+            let t: Treasury = self.zero_copy(&treasury)?;
+            let mut ixs = vec![];
+            for (mint, c) in [(t.mint_reserve, &m.reserve), (t.mint_staking, &m.staking)] {
+                let program = reserve::token_metadata::ID;
+                let (metadata, _) = Pubkey::find_program_address(
+                    &[b"metadata", program.as_ref(), mint.as_ref()],
+                    &program,
+                );
+                ixs.push(self.ix(
+                    reserve::accounts::TreasuryMetadata {
+                        signer: self.authority(),
+                        treasury,
+                        mint,
+                        metadata,
+                        metadata_program: program,
+                        rent: sysvar::rent::ID,
+                        system_program: system_program::ID,
+                    },
+                    reserve::instruction::TreasuryMetadata {
+                        name: c.name.clone(),
+                        symbol: c.symbol.clone(),
+                        uri: c.uri.clone(),
+                    },
+                ));
+            }
+            self.send(ixs)?;
+        }
+        Ok(())
+    }
+
+    fn crank_observe(&self) -> Result<()> {
+        let treasury = self.treasury();
+        let mut ixs = vec![];
+        for name in self.config.bonds.keys() {
+            let bond = self.bond(name)?;
+            let (observations, bump) = self.pda(&[b"bond_observations", bond.as_ref()]);
+            if self.rpc.get_account(&observations).is_err() {
+                ixs.push(self.ix(
+                    reserve::accounts::BondObservationsInitialize {
+                        signer: self.payer.pubkey(),
+                        bond,
+                        observations,
+                        system_program: system_program::ID,
+                    },
+                    reserve::instruction::BondObservationsInitialize { bump },
+                ));
+            }
+            ixs.push(self.ix(
+                reserve::accounts::BondObserve {
+                    treasury,
+                    bond,
+                    observations,
+                },
+                reserve::instruction::BondObserve {},
+            ));
+        }
+        self.send_direct(ixs)
+    }
+
+    fn create_bond(&self, name: &str) -> Result<()> {
+        let mint_bond = pubkey(&self.config.bond(name)?.mint)?;
+        let treasury = self.treasury();
+        let (bond, bump) = self.pda(&[b"bond", treasury.as_ref(), mint_bond.as_ref()]);
+        let mut ixs = vec![self.ix(
+            reserve::accounts::BondInitialize {
+                signer: self.authority(),
+                treasury,
+                role: self.role(&self.authority()).0,
+                bond,
+                mint_bond,
+                migration: self.pda(&[b"migration", bond.as_ref()]).0,
+                system_program: system_program::ID,
+            },
+            reserve::instruction::BondInitialize { _bump: bump },
+        )];
+        ixs.extend(self.create_token_account(&treasury, &mint_bond)?);
+        self.send(ixs)?;
+        println!("bond {}", bond);
+        Ok(())
+    }
+
+    fn configure_bond(&self, name: &str) -> Result<()> {
+        let c = self.config.bond(name)?;
+        self.send(vec![
+            self.ix(
+                reserve::accounts::BondConfigure {
+                    signer: self.authority(),
+                    treasury: self.treasury(),
+                    role: self.role(&self.authority()).0,
+                    bond: self.bond(name)?,
+                },
+                reserve::instruction::BondConfigure {
+                    vesting_period: c.vesting_period,
+                    rfv_rate: c.rfv_rate,
+                    min_price: c.min_price,
+                    max_payout: c.max_payout,
+                    max_debt: c.max_debt,
+                    fee: c.fee,
+                    bcv: c.bcv,
+                },
+            ),
+            self.ix(
+                reserve::accounts::BondConfigure {
+                    signer: self.authority(),
+                    treasury: self.treasury(),
+                    role: self.role(&self.authority()).0,
+                    bond: self.bond(name)?,
+                },
+                reserve::instruction::BondConfigureCaps {
+                    max_debt_user: c.max_debt_user,
+                    max_payout_slot: c.max_payout_slot,
+                },
+            ),
+            self.ix(
+                reserve::accounts::BondConfigure {
+                    signer: self.authority(),
+                    treasury: self.treasury(),
+                    role: self.role(&self.authority()).0,
+                    bond: self.bond(name)?,
+                },
+                reserve::instruction::BondConfigureExit {
+                    exit_penalty: c.exit_penalty,
+                },
+            ),
+        ])
+    }
+
+    fn show_treasury(&self) -> Result<()> {
+        let key = self.treasury();
+        let t: Treasury = self.zero_copy(&key)?;
+        let mint_reserve: Mint = self.anchor_account(&{ t.mint_reserve })?;
+        let mint_staking: Mint = self.anchor_account(&{ t.mint_staking })?;
+        let pool: TokenAccount = self.anchor_account(&{ t.token_reserve_staking })?;
+        println!("treasury {}", key);
+        println!("  authority {}", { t.authority });
+        println!("  dao {}", { t.dao });
+        println!(
+            "  mint_reserve {} (supply {})",
+            { t.mint_reserve },
+            mint_reserve.supply
+        );
+        println!(
+            "  mint_staking {} (supply {})",
+            { t.mint_staking },
+            mint_staking.supply
+        );
+        println!(
+            "  token_reserve_staking {} (amount {})",
+            { t.token_reserve_staking },
+            pool.amount
+        );
+        println!("  token_staking_vesting {}", { t.token_staking_vesting });
+        println!("  total_reserves {}", { t.total_reserves });
+        println!("  staking_rate {}", { t.staking_rate });
+        println!("  staking_last {}", { t.staking_last });
+        println!("  staking_warmup {}", { t.staking_warmup });
+        println!("  staking_cooldown {}", { t.staking_cooldown });
+        println!("  staking_warmup_total {}", { t.staking_warmup_total });
+        println!("  staking_cooldown_total {}", { t.staking_cooldown_total });
+        println!("  lock_max_duration {}", { t.lock_max_duration });
+        println!("  lock_boost_rate {}", { t.lock_boost_rate });
+        println!("  governance_quorum {}", { t.governance_quorum });
+        println!("  governance_threshold {}", { t.governance_threshold });
+
+        let e: Epoch = self.zero_copy(&self.epoch())?;
+        println!("epoch {}", { e.number });
+        println!("  length {}", { e.length });
+        println!("  end {}", { e.end });
+        println!("  distribute {}", { e.distribute });
+        println!("  adjustment_rate {}", { e.adjustment_rate });
+        println!("  adjustment_target {}", { e.adjustment_target });
+        println!("  gauge_budget {}", { e.gauge_budget });
+        println!("  gauge_votes {}", { e.gauge_votes });
+        println!("  gauge_votes_last {}", { e.gauge_votes_last });
+        let mut history: Vec<EpochIndex> = e
+            .history
+            .iter()
+            .copied()
+            .filter(|h| h.timestamp > 0)
+            .collect();
+        history.sort_by_key(|h| h.number);
+        for h in history.iter().rev().take(5) {
+            println!("  [{}] index {} at {}", { h.number }, { h.index }, {
+                h.timestamp
+            });
+        }
+
+        let lock_curve = self.pda(&[b"lock_curve", key.as_ref()]).0;
+        if let Ok(mut c) = self.zero_copy::<LockCurve>(&lock_curve) {
+            let now = unix_now()?;
+            c.checkpoint(now);
+            println!("lock_curve {}", lock_curve);
+            println!("  period {}", { c.period });
+            println!("  weight {}", c.weight(now) / ONE as u128);
+        }
+
+        // same as the protocol_metrics instruction
+        let m = protocol_metrics(
+            &t,
+            &e,
+            mint_reserve.supply,
+            mint_staking.supply,
+            pool.amount,
+        )
+        .map_err(|e| anyhow!("computing metrics: {:?}", e))?;
+        println!("metrics");
+        println!("  backing_per_token {}", m.backing_per_token);
+        println!("  staking_index {}", m.staking_index);
+        println!("  reward_rate {}", m.reward_rate);
+        println!("  roi_five_day {}", m.roi_five_day);
+        println!("  apy {}", m.apy);
+        println!("  runway_days {}", m.runway_days);
+        Ok(())
+    }
+
+    fn show_bond(&self, name: &str) -> Result<()> {
+        let key = self.bond(name)?;
+        let b: Bond = self.zero_copy(&key)?;
+        println!("bond {} ({})", key, name);
+        println!("  mint_bond {}", { b.mint_bond });
+        println!("  token_decimals {}", { b.token_decimals });
+        println!("  vesting_period {}", { b.vesting_period });
+        println!("  rfv_rate {}", { b.rfv_rate });
+        println!("  min_price {}", { b.min_price });
+        println!("  max_payout {}", { b.max_payout });
+        println!("  max_debt {}", { b.max_debt });
+        println!("  fee {}", { b.fee });
+        println!("  bcv {}", { b.bcv });
+        println!("  total_debt {}", { b.total_debt });
+        println!("  total_debt_last {}", { b.total_debt_last });
+        println!("  total_debt_alltime {}", { b.total_debt_alltime });
+        println!("  paused {}", { b.paused });
+        println!("  max_debt_user {}", { b.max_debt_user });
+        println!("  max_payout_slot {}", { b.max_payout_slot });
+        println!("  exit_penalty {}", { b.exit_penalty });
+        println!(
+            "  gauge_votes {} (epoch {}, previous {})",
+            { b.gauge_votes },
+            { b.gauge_epoch },
+            { b.gauge_votes_last }
+        );
+        Ok(())
+    }
+
+    fn show_user(&self, wallet: Option<String>) -> Result<()> {
+        let wallet = match wallet {
+            Some(wallet) => pubkey(&wallet)?,
+            None => self.payer.pubkey(),
+        };
+        let treasury = self.treasury();
+        let (key, _) = self.pda(&[b"user", treasury.as_ref(), wallet.as_ref()]);
+        let u: User = self.zero_copy(&key)?;
+        let now = unix_now()?;
+        println!("user {} (signer {})", key, { u.signer });
+        let bonds = u.bonds;
+        for (i, b) in bonds.iter().enumerate() {
+            if { b.bond } == Pubkey::default() {
+                continue;
+            }
+            let vested = vested_amount(&b.schedule(), b.staked, now)
+                .map_err(|e| anyhow!("vesting failed: {:?}", e))?;
+            println!(
+                "  [{}] bond {} price {} payout {} staked {} claimed {} vested {} vesting {} {}+{}",
+                i,
+                { b.bond },
+                { b.price },
+                { b.payout },
+                { b.staked },
+                { b.claimed },
+                vested,
+                schedule_name(&b.schedule()),
+                { b.vesting_start },
+                { b.vesting_period },
+            );
+        }
+        if u.warmup_amount > 0 {
+            println!("  warmup {} until {}", { u.warmup_amount }, {
+                u.warmup_end
+            });
+        }
+        if u.cooldown_amount > 0 {
+            println!("  cooldown {} until {}", { u.cooldown_amount }, {
+                u.cooldown_end
+            });
+        }
+        Ok(())
+    }
+
+    fn quote(&self, name: &str, amount: u64) -> Result<()> {
+        let t: Treasury = self.zero_copy(&self.treasury())?;
+        let bond = self.bond(name)?;
+        let b: Bond = self.zero_copy(&bond)?;
+        let mint_staking: Mint = self.anchor_account(&{ t.mint_staking })?;
+        let pool: TokenAccount = self.anchor_account(&{ t.token_reserve_staking })?;
+        let now = unix_now()?;
+        if b.max_payout == 0 {
+            bail!("bond not configured");
+        }
+
+        let q = bond_quote(&b, t.total_reserves, amount, now)
+            .map_err(|e| anyhow!("quote failed: {:?}", e))?;
+        let staked = staking_shares(q.payout, mint_staking.supply, t.staking_pool(pool.amount))
+            .map_err(|e| anyhow!("quote failed: {:?}", e))?;
+        println!("price {}", q.price);
+        println!("value {}", q.value);
+        println!("payout {}", q.payout);
+        println!("staked {}", staked);
+        println!("fee {}", q.fee);
+        println!("debt_ratio {}", q.debt_ratio);
+        println!("vesting_period {}", { b.vesting_period });
+        let (schedule, _) = self.pda(&[b"bond_schedule", bond.as_ref()]);
+        if let Ok(s) = self.zero_copy::<BondSchedule>(&schedule) {
+            println!(
+                "vesting_schedule {}",
+                schedule_name(&VestingSchedule {
+                    kind: s.kind,
+                    cliff: s.cliff,
+                    ..Default::default()
+                })
+            );
+        }
+        if q.total_debt >= b.max_debt {
+            println!("warning: bond at max debt");
+        }
+        if q.payout <= ONE / 100 {
+            println!("warning: payout too small");
+        }
+        if q.payout > q.max_payout {
+            println!("warning: payout above max payout {}", q.max_payout);
+        }
+        if b.max_debt_user > 0 && q.value > b.max_debt_user {
+            println!("warning: value above max debt per user {}", {
+                b.max_debt_user
+            });
+        }
+        let (oracle, _) = self.pda(&[b"oracle", self.treasury().as_ref()]);
+        if let Ok(o) = self.zero_copy::<Oracle>(&oracle) {
+            let mint_reserve: Mint = self.anchor_account(&{ t.mint_reserve })?;
+            let market = o.price as f64;
+            let backing = t.total_reserves as f64 / mint_reserve.supply.max(1) as f64;
+            println!("market_price {} (updated {})", { o.price }, { o.updated });
+            if o.kind == ORACLE_POOL {
+                println!("pool_price {} (averaged over {}s)", { o.spot }, {
+                    o.window
+                });
+            }
+            println!(
+                "discount {:.2}%",
+                (market - q.price as f64) / market.max(1.0) * 100.0
+            );
+            if q.price > o.price {
+                println!("warning: bond priced above market");
+            }
+            if (q.price as f64) < backing * ONE as f64 {
+                println!("warning: bond priced below backing");
+            }
+            if now.saturating_sub(o.updated) > o.max_age {
+                println!("warning: oracle price stale, deposits need an oracle_update first");
+            }
+        }
+        Ok(())
+    }
+
+    // points the oracle at a pool's reserve and quote token accounts, or at
+    // a price feed
+    #[allow(clippy::too_many_arguments)]
+    fn set_oracle(
+        &self,
+        kind: &str,
+        source: &str,
+        source_quote: Option<String>,
+        quote_decimals: u64,
+        max_age: u64,
+        window: u64,
+        interval: u64,
+    ) -> Result<()> {
+        let kind = match kind {
+            "pool" => ORACLE_POOL,
+            "feed" => ORACLE_FEED,
+            _ => bail!("unknown oracle kind {:?}", kind),
+        };
+        let source = pubkey(source)?;
+        let source_quote = match source_quote {
+            Some(key) => pubkey(&key)?,
+            None if kind == ORACLE_FEED => source,
+            None => bail!("pool oracles need the pool's quote token account"),
+        };
+        let treasury = self.treasury();
+        let (oracle, bump) = self.pda(&[b"oracle", treasury.as_ref()]);
+        let ix = if self.rpc.get_account(&oracle).is_ok() {
+            self.ix(
+                reserve::accounts::OracleConfigure {
+                    signer: self.authority(),
+                    treasury,
+                    oracle,
+                    source,
+                    source_quote,
+                },
+                reserve::instruction::OracleConfigure {
+                    kind,
+                    quote_decimals,
+                    max_age,
+                    window,
+                    interval,
+                },
+            )
+        } else {
+            self.ix(
+                reserve::accounts::OracleInitialize {
+                    signer: self.authority(),
+                    treasury,
+                    oracle,
+                    source,
+                    source_quote,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::OracleInitialize {
+                    bump,
+                    kind,
+                    quote_decimals,
+                    max_age,
+                    window,
+                    interval,
+                },
+            )
+        };
+        self.send(vec![ix])
+    }
+
+    fn crank_rebase(&self) -> Result<()> {
+        let treasury = self.treasury();
+        let t: Treasury = self.zero_copy(&treasury)?;
+        let e: Epoch = self.zero_copy(&self.epoch())?;
+        let mut ixs = vec![self.ix(
+            reserve::accounts::StakingRewards {
+                treasury,
+                epoch: self.epoch(),
+                lock_curve: self.pda(&[b"lock_curve", treasury.as_ref()]).0,
+                mint_reserve: t.mint_reserve,
+                mint_staking: t.mint_staking,
+                token_reserve_staking: t.token_reserve_staking,
+                token_staking_vesting: t.token_staking_vesting,
+                token_program: token::ID,
+            },
+            reserve::instruction::StakingRewards {},
+        )];
+        // split the gauge budget between bonds by last epoch's votes
+        if e.gauge_budget > 0 {
+            for name in self.config.bonds.keys() {
+                ixs.push(self.ix(
+                    reserve::accounts::BondGaugeApply {
+                        treasury,
+                        epoch: self.epoch(),
+                        bond: self.bond(name)?,
+                    },
+                    reserve::instruction::BondGaugeApply {},
+                ));
+            }
+        }
+        self.send(ixs)
+    }
+
+    fn grant_role(&self, wallet: &str, roles: &[String]) -> Result<()> {
+        let member = pubkey(wallet)?;
+        let treasury = self.treasury();
+        let (role, bump) = self.role(&member);
+        let mut ixs = vec![];
+        if self.rpc.get_account(&role).is_err() {
+            ixs.push(self.ix(
+                reserve::accounts::RoleInitialize {
+                    signer: self.authority(),
+                    treasury,
+                    member,
+                    role,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::RoleInitialize { bump },
+            ));
+        }
+        ixs.push(self.ix(
+            reserve::accounts::RoleConfigure {
+                signer: self.authority(),
+                treasury,
+                role,
+            },
+            reserve::instruction::RoleGrant {
+                roles: parse_roles(roles)?,
+            },
+        ));
+        self.send(ixs)
+    }
+
+    fn revoke_role(&self, wallet: &str, roles: &[String]) -> Result<()> {
+        self.send(vec![self.ix(
+            reserve::accounts::RoleConfigure {
+                signer: self.authority(),
+                treasury: self.treasury(),
+                role: self.role(&pubkey(wallet)?).0,
+            },
+            reserve::instruction::RoleRevoke {
+                roles: parse_roles(roles)?,
+            },
+        )])
+    }
+
+    fn pause_bond(&self, name: &str, paused: bool) -> Result<()> {
+        self.send(vec![self.ix(
+            reserve::accounts::BondPause {
+                signer: self.authority(),
+                treasury: self.treasury(),
+                role: self.role(&self.authority()).0,
+                bond: self.bond(name)?,
+            },
+            reserve::instruction::BondPause { paused },
+        )])
+    }
+
+    fn schedule_bond(&self, name: &str, kind: &str, cliff: u64) -> Result<()> {
+        let kind = match kind {
+            "linear" => VESTING_LINEAR,
+            "cliff" => VESTING_CLIFF,
+            "monthly" => VESTING_MONTHLY,
+            "exponential" => VESTING_EXPONENTIAL,
+            _ => bail!("unknown vesting schedule {:?}", kind),
+        };
+        let bond = self.bond(name)?;
+        let (schedule, bump) = self.pda(&[b"bond_schedule", bond.as_ref()]);
+        let ix = if self.rpc.get_account(&schedule).is_ok() {
+            self.ix(
+                reserve::accounts::BondScheduleConfigure {
+                    signer: self.authority(),
+                    treasury: self.treasury(),
+                    role: self.role(&self.authority()).0,
+                    bond,
+                    schedule,
+                },
+                reserve::instruction::BondScheduleConfigure { kind, cliff },
+            )
+        } else {
+            self.ix(
+                reserve::accounts::BondScheduleInitialize {
+                    signer: self.authority(),
+                    treasury: self.treasury(),
+                    role: self.role(&self.authority()).0,
+                    bond,
+                    schedule,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::BondScheduleInitialize { bump, kind, cliff },
+            )
+        };
+        self.send(vec![ix])
+    }
+
+    fn allowlist_bond(&self, name: &str, path: Option<PathBuf>) -> Result<()> {
+        let mut root = [0; 32];
+        if let Some(path) = path {
+            let mut leaves = vec![];
+            for line in std::fs::read_to_string(&path)?.lines() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let (wallet, max_amount) = line
+                    .split_once(',')
+                    .ok_or_else(|| anyhow!("invalid allowlist line {:?}", line))?;
+                let wallet = pubkey(wallet.trim())?;
+                let max_amount: u64 = max_amount.trim().parse()?;
+                leaves.push((
+                    wallet,
+                    max_amount,
+                    hashv(&[wallet.as_ref(), &max_amount.to_le_bytes()]).to_bytes(),
+                ));
+            }
+            let layers = merkle_layers(leaves.iter().map(|l| l.2).collect());
+            root = layers
+                .last()
+                .and_then(|l| l.first())
+                .copied()
+                .unwrap_or_default();
+            for (i, (wallet, max_amount, _)) in leaves.iter().enumerate() {
+                let proof: Vec<String> = merkle_proof(&layers, i)
+                    .iter()
+                    .map(|p| p.iter().map(|b| format!("{:02x}", b)).collect())
+                    .collect();
+                println!("{} {} {}", wallet, max_amount, proof.join(","));
+            }
+        }
+
+        let bond = self.bond(name)?;
+        let (allowlist, bump) = self.pda(&[b"bond_allowlist", bond.as_ref()]);
+        let ix = if self.rpc.get_account(&allowlist).is_ok() {
+            self.ix(
+                reserve::accounts::BondAllowlistConfigure {
+                    signer: self.authority(),
+                    treasury: self.treasury(),
+                    role: self.role(&self.authority()).0,
+                    bond,
+                    allowlist,
+                },
+                reserve::instruction::BondAllowlistConfigure { root },
+            )
+        } else {
+            self.ix(
+                reserve::accounts::BondAllowlistInitialize {
+                    signer: self.authority(),
+                    treasury: self.treasury(),
+                    role: self.role(&self.authority()).0,
+                    bond,
+                    allowlist,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::BondAllowlistInitialize { bump, root },
+            )
+        };
+        self.send(vec![ix])
+    }
+
+    fn distribute_fees(&self, mint: &str) -> Result<()> {
+        let mint = pubkey(mint)?;
+        let fee_split = self.fee_split();
+        let f: FeeSplit = self.zero_copy(&fee_split)?;
+        let mut ixs = vec![];
+        let mut ix = self.ix(
+            reserve::accounts::FeeSplitDistribute {
+                fee_split,
+                token_vault: spl_associated_token_account::get_associated_token_address(
+                    &fee_split, &mint,
+                ),
+                token_program: token::ID,
+            },
+            reserve::instruction::FeeSplitDistribute {},
+        );
+        for recipient in f.recipients.iter().filter(|r| r.weight > 0) {
+            let owner = recipient.owner;
+            ixs.extend(self.create_token_account(&owner, &mint)?);
+            ix.accounts.push(AccountMeta::new(
+                spl_associated_token_account::get_associated_token_address(&owner, &mint),
+                false,
+            ));
+        }
+        ixs.push(ix);
+        self.send_direct(ixs)
+    }
+
+    // each account is closed into a migration buffer then recreated, in two
+    // transactions as it can't be recreated in the one that closed it
+    fn migrate(&self) -> Result<()> {
+        let key = pubkey(&self.config.treasury.key)?;
+        let (treasury, bump) = self.pda(&[b"treasury", key.as_ref()]);
+        if !self.migrated::<Treasury>(&treasury)? {
+            let (migration, migration_bump) = self.pda(&[b"migration", treasury.as_ref()]);
+            println!("migrating treasury {}", treasury);
+            self.send(vec![self.ix(
+                reserve::accounts::MigrateTreasuryBegin {
+                    signer: self.authority(),
+                    treasury,
+                    migration,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::MigrateTreasuryBegin {
+                    bump: migration_bump,
+                },
+            )])?;
+            self.send(vec![self.ix(
+                reserve::accounts::MigrateTreasuryFinish {
+                    signer: self.authority(),
+                    treasury,
+                    migration,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::MigrateTreasuryFinish {
+                    _key: key,
+                    _bump: bump,
+                },
+            )])?;
+        }
+        for (name, config) in &self.config.bonds {
+            let mint_bond = pubkey(&config.mint)?;
+            let (bond, bump) = self.pda(&[b"bond", treasury.as_ref(), mint_bond.as_ref()]);
+            if self.migrated::<Bond>(&bond)? {
+                continue;
+            }
+            let (migration, migration_bump) = self.pda(&[b"migration", bond.as_ref()]);
+            println!("migrating bond {} {}", name, bond);
+            self.send(vec![self.ix(
+                reserve::accounts::MigrateBondBegin {
+                    signer: self.authority(),
+                    treasury,
+                    bond,
+                    migration,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::MigrateBondBegin {
+                    bump: migration_bump,
+                },
+            )])?;
+            self.send(vec![self.ix(
+                reserve::accounts::MigrateBondFinish {
+                    signer: self.authority(),
+                    treasury,
+                    bond,
+                    mint_bond,
+                    migration,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::MigrateBondFinish { _bump: bump },
+            )])?;
+        }
+        Ok(())
+    }
+
+    fn approve_referrer(&self, wallet: &str, fee_max: u64) -> Result<()> {
+        let treasury = self.treasury();
+        let wallet = pubkey(wallet)?;
+        let (referrer, _) = self.pda(&[b"referrer", treasury.as_ref(), wallet.as_ref()]);
+        self.send(vec![self.ix(
+            reserve::accounts::ReferrerApprove {
+                signer: self.authority(),
+                treasury,
+                referrer,
+            },
+            reserve::instruction::ReferrerApprove { fee_max },
+        )])
+    }
+
+    fn create_multisig(&self, owners: &[String], threshold: u64) -> Result<()> {
+        let treasury = self.treasury();
+        let (multisig, bump) = self.pda(&[b"multisig", treasury.as_ref()]);
+        self.send(vec![self.ix(
+            reserve::accounts::MultisigCreate {
+                signer: self.payer.pubkey(),
+                treasury,
+                multisig,
+                system_program: system_program::ID,
+            },
+            reserve::instruction::MultisigCreate {
+                bump,
+                owners: owners.iter().map(|o| pubkey(o)).collect::<Result<_>>()?,
+                threshold,
+            },
+        )])?;
+        println!("multisig {}", multisig);
+        println!("multisig signer {}", self.multisig_signer());
+        Ok(())
+    }
+
+    fn approve_transaction(&self, transaction: &str) -> Result<()> {
+        self.send_direct(vec![self.ix(
+            reserve::accounts::MultisigApprove {
+                signer: self.payer.pubkey(),
+                multisig: self.multisig(),
+                transaction: pubkey(transaction)?,
+            },
+            reserve::instruction::MultisigApprove {},
+        )])
+    }
+
+    fn execute_transaction(&self, transaction: &str) -> Result<()> {
+        let key = pubkey(transaction)?;
+        let t: MultisigTransaction = self.anchor_account(&key)?;
+        let mut ix = self.ix(
+            reserve::accounts::MultisigExecute {
+                multisig: self.multisig(),
+                transaction: key,
+            },
+            reserve::instruction::MultisigExecute {},
+        );
+        // the invoked instruction's accounts, signed for by the program
+        for meta in t.metas.iter() {
+            ix.accounts.push(if meta.is_writable {
+                AccountMeta::new(meta.pubkey, false)
+            } else {
+                AccountMeta::new_readonly(meta.pubkey, false)
+            });
+        }
+        ix.accounts
+            .push(AccountMeta::new_readonly(self.program_id, false));
+        self.send_direct(vec![ix])
+    }
+
+    fn show_multisig(&self) -> Result<()> {
+        let key = self.multisig();
+        let m: Multisig = self.anchor_account(&key)?;
+        println!("multisig {}", key);
+        println!("  signer {}", self.multisig_signer());
+        println!("  threshold {} of {}", m.threshold, m.owners.len());
+        for owner in m.owners.iter() {
+            println!("  owner {}", owner);
+        }
+        println!("  owner_set_seqno {}", m.owner_set_seqno);
+        Ok(())
+    }
+
+    // signer of admin instructions
+    fn authority(&self) -> Pubkey {
+        if self.use_multisig {
+            self.multisig_signer()
+        } else {
+            self.payer.pubkey()
+        }
+    }
+
+    fn multisig(&self) -> Pubkey {
+        let treasury = self.treasury();
+        self.pda(&[b"multisig", treasury.as_ref()]).0
+    }
+
+    fn multisig_signer(&self) -> Pubkey {
+        let multisig = self.multisig();
+        self.pda(&[b"multisig_signer", multisig.as_ref()]).0
+    }
+
+    fn treasury(&self) -> Pubkey {
+        let key = pubkey(&self.config.treasury.key).expect("treasury key");
+        self.pda(&[b"treasury", key.as_ref()]).0
+    }
+
+    fn fee_split(&self) -> Pubkey {
+        let treasury = self.treasury();
+        self.pda(&[b"fee_split", treasury.as_ref()]).0
+    }
+
+    fn epoch(&self) -> Pubkey {
+        let treasury = self.treasury();
+        self.pda(&[b"epoch", treasury.as_ref()]).0
+    }
+
+    fn bond(&self, name: &str) -> Result<Pubkey> {
+        let mint_bond = pubkey(&self.config.bond(name)?.mint)?;
+        let treasury = self.treasury();
+        Ok(self
+            .pda(&[b"bond", treasury.as_ref(), mint_bond.as_ref()])
+            .0)
+    }
+
+    fn role(&self, member: &Pubkey) -> (Pubkey, u8) {
+        let treasury = self.treasury();
+        self.pda(&[b"role", treasury.as_ref(), member.as_ref()])
+    }
+
+    fn pda(&self, seeds: &[&[u8]]) -> (Pubkey, u8) {
+        Pubkey::find_program_address(seeds, &self.program_id)
+    }
+
+    fn ix(&self, accounts: impl ToAccountMetas, args: impl InstructionData) -> Instruction {
+        Instruction {
+            program_id: self.program_id,
+            accounts: accounts.to_account_metas(None),
+            data: args.data(),
+        }
+    }
+
+    // associated token account creation, skipped when it already exists
+    fn create_token_account(&self, owner: &Pubkey, mint: &Pubkey) -> Result<Vec<Instruction>> {
+        let address = spl_associated_token_account::get_associated_token_address(owner, mint);
+        if self.rpc.get_account(&address).is_ok() {
+            return Ok(vec![]);
+        }
+        println!("token account {} (owner {}, mint {})", address, owner, mint);
+        Ok(vec![
+            spl_associated_token_account::create_associated_token_account(
+                &self.payer.pubkey(),
+                owner,
+                mint,
+            ),
+        ])
+    }
+
+    fn zero_copy<T: bytemuck::Pod>(&self, key: &Pubkey) -> Result<T> {
+        let data = self.rpc.get_account_data(key)?;
+        let size = std::mem::size_of::<T>();
+        if data.len() < 8 + size {
+            bail!("account {} is too small", key);
+        }
+        Ok(*bytemuck::from_bytes::<T>(&data[8..8 + size]))
+    }
+
+    // whether an account has the current layout's size, layouts only grow
+    fn migrated<T>(&self, key: &Pubkey) -> Result<bool> {
+        let data = self.rpc.get_account_data(key)?;
+        Ok(data.len() == 8 + std::mem::size_of::<T>())
+    }
+
+    fn anchor_account<T: AccountDeserialize>(&self, key: &Pubkey) -> Result<T> {
+        let data = self.rpc.get_account_data(key)?;
+        T::try_deserialize(&mut data.as_slice()).map_err(|e| anyhow!("account {}: {:?}", key, e))
+    }
+
+    // with --multisig, reserve program instructions are proposed to the
+    // multisig instead of being sent
+    fn send(&self, ixs: Vec<Instruction>) -> Result<()> {
+        if !self.use_multisig {
+            return self.send_direct(ixs);
+        }
+        let multisig = self.multisig();
+        let mut proposed = vec![];
+        for ix in ixs {
+            if ix.program_id != self.program_id {
+                proposed.push(ix);
+                continue;
+            }
+            let key = Keypair::new().pubkey();
+            let (transaction, bump) =
+                self.pda(&[b"multisig_transaction", multisig.as_ref(), key.as_ref()]);
+            println!("multisig transaction {}", transaction);
+            proposed.push(
+                self.ix(
+                    reserve::accounts::MultisigTransactionCreate {
+                        signer: self.payer.pubkey(),
+                        multisig,
+                        transaction,
+                        system_program: system_program::ID,
+                    },
+                    reserve::instruction::MultisigTransactionCreate {
+                        bump,
+                        _key: key,
+                        metas: ix
+                            .accounts
+                            .iter()
+                            .map(|m| MultisigAccount {
+                                pubkey: m.pubkey,
+                                is_signer: m.is_signer,
+                                is_writable: m.is_writable,
+                            })
+                            .collect(),
+                        data: ix.data,
+                    },
+                ),
+            );
+        }
+        self.send_direct(proposed)
+    }
+
+    fn send_direct(&self, ixs: Vec<Instruction>) -> Result<()> {
+        let mut tx = Transaction::new_with_payer(&ixs, Some(&self.payer.pubkey()));
+        if self.dry_run {
+            for ix in &ixs {
+                println!("instruction {}", ix.program_id);
+                for meta in &ix.accounts {
+                    println!(
+                        "  {} {}{}",
+                        meta.pubkey,
+                        if meta.is_signer { "s" } else { "-" },
+                        if meta.is_writable { "w" } else { "-" },
+                    );
+                }
+                println!("  data {}", base64::encode(&ix.data));
+            }
+            println!("message {}", base64::encode(tx.message_data()));
+            return Ok(());
+        }
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        tx.sign(&[&self.payer], blockhash);
+        let signature = self.rpc.send_and_confirm_transaction_with_spinner(&tx)?;
+        println!("signature {}", signature);
+        Ok(())
+    }
+}
+
+fn unix_now() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+fn schedule_name(schedule: &VestingSchedule) -> String {
+    match schedule.kind {
+        VESTING_CLIFF => format!("cliff {}s", schedule.cliff),
+        VESTING_MONTHLY => "monthly".to_string(),
+        VESTING_EXPONENTIAL => "exponential".to_string(),
+        _ => "linear".to_string(),
+    }
+}
+
+// leaves first, each layer hashing sorted pairs of the one below, odd nodes
+// moving up as is
+fn merkle_layers(leaves: Vec<[u8; 32]>) -> Vec<Vec<[u8; 32]>> {
+    let mut layers = vec![leaves];
+    while layers.last().is_some_and(|l| l.len() > 1) {
+        let next = layers
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] if a <= b => hashv(&[a, b]).to_bytes(),
+                [a, b] => hashv(&[b, a]).to_bytes(),
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+        layers.push(next);
+    }
+    layers
+}
+
+fn merkle_proof(layers: &[Vec<[u8; 32]>], mut index: usize) -> Vec<[u8; 32]> {
+    let mut proof = vec![];
+    for layer in &layers[..layers.len() - 1] {
+        if let Some(sibling) = layer.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        index /= 2;
+    }
+    proof
+}
+
+fn parse_roles(names: &[String]) -> Result<u64> {
+    let mut roles = 0;
+    for name in names {
+        roles |= match name.as_str() {
+            "bond-manager" => ROLE_BOND_MANAGER,
+            "risk-manager" => ROLE_RISK_MANAGER,
+            "treasurer" => ROLE_TREASURER,
+            "guardian" => ROLE_GUARDIAN,
+            "all" => ROLE_BOND_MANAGER | ROLE_RISK_MANAGER | ROLE_TREASURER | ROLE_GUARDIAN,
+            _ => bail!("unknown role {:?}", name),
+        };
+    }
+    Ok(roles)
+}
@@ -0,0 +1,306 @@
+use crate::events::Event;
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS deposits (
+    signature TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    signer TEXT NOT NULL,
+    treasury TEXT NOT NULL,
+    bond TEXT NOT NULL,
+    user TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    price INTEGER NOT NULL,
+    payout INTEGER NOT NULL,
+    staked INTEGER NOT NULL,
+    -- referrer pda, or the default pubkey
+    referrer TEXT NOT NULL,
+    referrer_fee INTEGER NOT NULL,
+    PRIMARY KEY (signature, idx)
+);
+CREATE INDEX IF NOT EXISTS deposits_signer ON deposits (signer);
+CREATE INDEX IF NOT EXISTS deposits_bond ON deposits (bond);
+
+CREATE TABLE IF NOT EXISTS claims (
+    signature TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    signer TEXT NOT NULL,
+    treasury TEXT NOT NULL,
+    bond TEXT NOT NULL,
+    user TEXT NOT NULL,
+    done INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    PRIMARY KEY (signature, idx)
+);
+CREATE INDEX IF NOT EXISTS claims_signer ON claims (signer);
+
+-- kind is 'stake' or 'unstake'
+CREATE TABLE IF NOT EXISTS stakes (
+    signature TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    signer TEXT NOT NULL,
+    treasury TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    staked INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    PRIMARY KEY (signature, idx)
+);
+CREATE INDEX IF NOT EXISTS stakes_signer ON stakes (signer);
+
+CREATE TABLE IF NOT EXISTS rebases (
+    signature TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    treasury TEXT NOT NULL,
+    epoch INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    pool INTEGER NOT NULL,
+    -- reserve tokens per staking token after the rebase, 1e9
+    staking_index INTEGER NOT NULL,
+    -- reserve tokens of `amount` set aside for locks
+    boost INTEGER NOT NULL,
+    PRIMARY KEY (signature, idx)
+);
+
+-- newest signature ingested from rpc, per program
+CREATE TABLE IF NOT EXISTS cursors (
+    program TEXT PRIMARY KEY,
+    signature TEXT NOT NULL
+);
+";
+
+pub struct Tx<'a> {
+    pub signature: &'a str,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+}
+
+pub struct Db {
+    conn: Connection,
+}
+
+#[derive(Debug)]
+pub struct UserPnl {
+    pub signer: String,
+    pub deposits: i64,
+    // rfv paid into bonds
+    pub bond_value: i64,
+    // reserve tokens owed by bonds
+    pub bond_payout: i64,
+    // staking tokens claimed from vesting
+    pub claimed: i64,
+    // reserve tokens staked and unstaked
+    pub staked: i64,
+    pub unstaked: i64,
+}
+
+#[derive(Debug)]
+pub struct BondVolume {
+    pub bond: String,
+    pub deposits: i64,
+    // in the bond token's smallest unit
+    pub amount: i64,
+    pub value: i64,
+    pub payout: i64,
+    pub first_time: Option<i64>,
+    pub last_time: Option<i64>,
+}
+
+impl UserPnl {
+    // reserve tokens taken out minus everything put in, valuing rfv 1:1
+    // with reserve tokens; positions still vesting or staked are not counted
+    pub fn realized(&self) -> i64 {
+        self.unstaked - self.staked - self.bond_value
+    }
+}
+
+impl Db {
+    pub fn open(path: &Path) -> Result<Db> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Db { conn })
+    }
+
+    // inserts are idempotent so the same transaction can be ingested twice
+    pub fn insert(&self, tx: &Tx, idx: usize, event: &Event) -> Result<()> {
+        let (signature, idx, slot, time) =
+            (tx.signature, idx as i64, tx.slot as i64, tx.block_time);
+        match event {
+            Event::BondDeposit(e) => self.conn.execute(
+                "INSERT OR IGNORE INTO deposits VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![
+                    signature,
+                    idx,
+                    slot,
+                    time,
+                    e.signer.to_string(),
+                    e.treasury.to_string(),
+                    e.bond.to_string(),
+                    e.user.to_string(),
+                    e.amount as i64,
+                    e.price as i64,
+                    e.payout as i64,
+                    e.staked as i64,
+                    e.referrer.to_string(),
+                    e.referrer_fee as i64,
+                ],
+            )?,
+            Event::BondWithdraw(e) => self.conn.execute(
+                "INSERT OR IGNORE INTO claims VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    signature,
+                    idx,
+                    slot,
+                    time,
+                    e.signer.to_string(),
+                    e.treasury.to_string(),
+                    e.bond.to_string(),
+                    e.user.to_string(),
+                    e.done,
+                    e.amount as i64,
+                ],
+            )?,
+            // everything left is claimed at once, the penalty isn't recorded
+            Event::BondExit(e) => self.conn.execute(
+                "INSERT OR IGNORE INTO claims VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1, ?9)",
+                params![
+                    signature,
+                    idx,
+                    slot,
+                    time,
+                    e.signer.to_string(),
+                    e.treasury.to_string(),
+                    e.bond.to_string(),
+                    e.user.to_string(),
+                    e.amount as i64,
+                ],
+            )?,
+            Event::StakingDeposit(e) => self.conn.execute(
+                "INSERT OR IGNORE INTO stakes VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'stake', ?7, ?8)",
+                params![
+                    signature,
+                    idx,
+                    slot,
+                    time,
+                    e.signer.to_string(),
+                    e.treasury.to_string(),
+                    e.staked as i64,
+                    e.amount as i64,
+                ],
+            )?,
+            Event::StakingWithdraw(e) => self.conn.execute(
+                "INSERT OR IGNORE INTO stakes VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'unstake', ?7, ?8)",
+                params![
+                    signature,
+                    idx,
+                    slot,
+                    time,
+                    e.signer.to_string(),
+                    e.treasury.to_string(),
+                    e.staked as i64,
+                    e.amount as i64,
+                ],
+            )?,
+            Event::StakingRebase(e) => self.conn.execute(
+                "INSERT OR IGNORE INTO rebases VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    signature,
+                    idx,
+                    slot,
+                    time,
+                    e.treasury.to_string(),
+                    e.epoch as i64,
+                    e.amount as i64,
+                    e.pool as i64,
+                    e.index as i64,
+                    e.boost as i64,
+                ],
+            )?,
+        };
+        Ok(())
+    }
+
+    pub fn cursor(&self, program: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT signature FROM cursors WHERE program = ?1",
+                params![program],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub fn set_cursor(&self, program: &str, signature: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO cursors VALUES (?1, ?2)",
+            params![program, signature],
+        )?;
+        Ok(())
+    }
+
+    pub fn user_pnl(&self, signer: Option<&str>) -> Result<Vec<UserPnl>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signer,
+                SUM(deposits), SUM(bond_value), SUM(bond_payout),
+                SUM(claimed), SUM(staked), SUM(unstaked)
+            FROM (
+                SELECT signer, 1 AS deposits, CAST(payout * (price / 1000000000.0) AS INTEGER) AS bond_value,
+                    payout AS bond_payout, 0 AS claimed, 0 AS staked, 0 AS unstaked
+                FROM deposits
+                UNION ALL
+                SELECT signer, 0, 0, 0, amount, 0, 0 FROM claims
+                UNION ALL
+                SELECT signer, 0, 0, 0, 0, amount, 0 FROM stakes WHERE kind = 'stake'
+                UNION ALL
+                SELECT signer, 0, 0, 0, 0, 0, amount FROM stakes WHERE kind = 'unstake'
+            )
+            WHERE ?1 IS NULL OR signer = ?1
+            GROUP BY signer
+            ORDER BY signer",
+        )?;
+        let rows = stmt.query_map(params![signer], |row| {
+            Ok(UserPnl {
+                signer: row.get(0)?,
+                deposits: row.get(1)?,
+                bond_value: row.get(2)?,
+                bond_payout: row.get(3)?,
+                claimed: row.get(4)?,
+                staked: row.get(5)?,
+                unstaked: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn bond_volume(&self) -> Result<Vec<BondVolume>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT bond, COUNT(*), SUM(amount), CAST(SUM(payout * (price / 1000000000.0)) AS INTEGER), SUM(payout),
+                MIN(block_time), MAX(block_time)
+            FROM deposits
+            GROUP BY bond
+            ORDER BY bond",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(BondVolume {
+                bond: row.get(0)?,
+                deposits: row.get(1)?,
+                amount: row.get(2)?,
+                value: row.get(3)?,
+                payout: row.get(4)?,
+                first_time: row.get(5)?,
+                last_time: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
@@ -0,0 +1,170 @@
+use anchor_lang::prelude::{borsh, Pubkey};
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{anyhow, Result};
+use reserve::{
+    EventBondDeposit, EventBondExit, EventBondWithdraw, EventStakingDeposit, EventStakingRebase,
+    EventStakingWithdraw,
+};
+
+pub enum Event {
+    BondDeposit(EventBondDeposit),
+    BondWithdraw(EventBondWithdraw),
+    BondExit(EventBondExit),
+    StakingDeposit(EventStakingDeposit),
+    StakingWithdraw(EventStakingWithdraw),
+    StakingRebase(EventStakingRebase),
+}
+
+// layouts emitted by earlier versions of the program, decoded into the
+// current events with the fields they didn't have left at zero
+
+// before referrer fees
+#[derive(AnchorDeserialize)]
+struct EventBondDepositV1 {
+    signer: Pubkey,
+    treasury: Pubkey,
+    bond: Pubkey,
+    user: Pubkey,
+    amount: u64,
+    price: u64,
+    payout: u64,
+    staked: u64,
+}
+
+// continuous rewards, before epochs
+#[derive(AnchorDeserialize)]
+struct EventStakingRebaseV1 {
+    treasury: Pubkey,
+    amount: u64,
+    pool: u64,
+}
+
+// before locks
+#[derive(AnchorDeserialize)]
+struct EventStakingRebaseV2 {
+    treasury: Pubkey,
+    epoch: u64,
+    amount: u64,
+    pool: u64,
+    index: u64,
+}
+
+impl From<EventBondDepositV1> for EventBondDeposit {
+    fn from(e: EventBondDepositV1) -> Self {
+        EventBondDeposit {
+            signer: e.signer,
+            treasury: e.treasury,
+            bond: e.bond,
+            user: e.user,
+            amount: e.amount,
+            price: e.price,
+            payout: e.payout,
+            staked: e.staked,
+            referrer: Pubkey::default(),
+            referrer_fee: 0,
+        }
+    }
+}
+
+impl From<EventStakingRebaseV1> for EventStakingRebase {
+    fn from(e: EventStakingRebaseV1) -> Self {
+        EventStakingRebase {
+            treasury: e.treasury,
+            epoch: 0,
+            amount: e.amount,
+            pool: e.pool,
+            index: 0,
+            boost: 0,
+        }
+    }
+}
+
+impl From<EventStakingRebaseV2> for EventStakingRebase {
+    fn from(e: EventStakingRebaseV2) -> Self {
+        EventStakingRebase {
+            treasury: e.treasury,
+            epoch: e.epoch,
+            amount: e.amount,
+            pool: e.pool,
+            index: e.index,
+            boost: 0,
+        }
+    }
+}
+
+// decodes the events emitted by `program_id` from a transaction's log
+// messages, ignoring logs of other programs (including ones it invokes);
+// events that match none of their known layouts are returned as errors
+pub fn parse_logs(program_id: &Pubkey, logs: &[String]) -> Vec<Result<Event>> {
+    let program = program_id.to_string();
+    let mut events = vec![];
+    // for each program frame, whether it's the reserve program
+    let mut stack: Vec<bool> = vec![];
+    for log in logs {
+        let data = log
+            .strip_prefix("Program log: ")
+            .or_else(|| log.strip_prefix("Program data: "));
+        if let Some(data) = data {
+            if stack.last() == Some(&true) {
+                // plain msg! logs aren't base64 and aren't events
+                if let Ok(data) = base64::decode(data) {
+                    if let Some(event) = decode(&data).transpose() {
+                        events.push(event);
+                    }
+                }
+            }
+            continue;
+        }
+        let parts: Vec<&str> = log.split_whitespace().collect();
+        if parts.len() < 3 || parts[0] != "Program" {
+            continue;
+        }
+        match parts[2] {
+            "invoke" => stack.push(parts[1] == program),
+            "success" | "failed:" => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+// None when `data` isn't one of the indexed events
+pub fn decode(data: &[u8]) -> Result<Option<Event>> {
+    if data.len() < 8 {
+        return Ok(None);
+    }
+    let (discriminator, rest) = data.split_at(8);
+    let event = if discriminator == EventBondDeposit::discriminator() {
+        EventBondDeposit::try_from_slice(rest)
+            .or_else(|_| EventBondDepositV1::try_from_slice(rest).map(Into::into))
+            .map(Event::BondDeposit)
+            .map_err(|e| anyhow!("EventBondDeposit: {}", e))
+    } else if discriminator == EventBondWithdraw::discriminator() {
+        EventBondWithdraw::try_from_slice(rest)
+            .map(Event::BondWithdraw)
+            .map_err(|e| anyhow!("EventBondWithdraw: {}", e))
+    } else if discriminator == EventBondExit::discriminator() {
+        EventBondExit::try_from_slice(rest)
+            .map(Event::BondExit)
+            .map_err(|e| anyhow!("EventBondExit: {}", e))
+    } else if discriminator == EventStakingDeposit::discriminator() {
+        EventStakingDeposit::try_from_slice(rest)
+            .map(Event::StakingDeposit)
+            .map_err(|e| anyhow!("EventStakingDeposit: {}", e))
+    } else if discriminator == EventStakingWithdraw::discriminator() {
+        EventStakingWithdraw::try_from_slice(rest)
+            .map(Event::StakingWithdraw)
+            .map_err(|e| anyhow!("EventStakingWithdraw: {}", e))
+    } else if discriminator == EventStakingRebase::discriminator() {
+        EventStakingRebase::try_from_slice(rest)
+            .or_else(|_| EventStakingRebaseV2::try_from_slice(rest).map(Into::into))
+            .or_else(|_| EventStakingRebaseV1::try_from_slice(rest).map(Into::into))
+            .map(Event::StakingRebase)
+            .map_err(|e| anyhow!("EventStakingRebase: {}", e))
+    } else {
+        return Ok(None);
+    };
+    event.map(Some)
+}
@@ -0,0 +1,218 @@
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use db::{Db, Tx};
+use serde::Deserialize;
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+mod db;
+mod events;
+#[cfg(test)]
+mod tests;
+
+#[derive(Parser)]
+#[clap(
+    name = "reserve-indexer",
+    about = "Index reserve program events into sqlite"
+)]
+struct Opts {
+    #[clap(long, default_value = "reserve.db")]
+    db: PathBuf,
+    #[clap(long, default_value = "6SMGNVogDVutJ8TpuLkyKUA8aWMbe8xpH5nC9ADw2PXB")]
+    program_id: String,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Ingest captured transaction logs, one json object per line:
+    /// {"signature": "..", "slot": 1, "block_time": 1, "err": null, "logs": [".."]}
+    IngestFile { path: PathBuf },
+    /// Ingest every transaction of the program since the last run
+    IngestRpc {
+        #[clap(long, default_value = "http://127.0.0.1:8899")]
+        url: String,
+    },
+    /// Per user bond and staking flows
+    Pnl { user: Option<String> },
+    /// Per bond deposit volume
+    Volume,
+}
+
+#[derive(Deserialize)]
+struct CapturedTx {
+    signature: String,
+    slot: u64,
+    block_time: Option<i64>,
+    #[serde(default)]
+    err: Option<serde_json::Value>,
+    logs: Vec<String>,
+}
+
+fn main() -> Result<()> {
+    let opts = Opts::parse();
+    let db = Db::open(&opts.db)?;
+    let program_id =
+        Pubkey::from_str(&opts.program_id).map_err(|_| anyhow!("invalid program id"))?;
+
+    match opts.command {
+        Command::IngestFile { path } => {
+            let count = ingest_file(&db, &program_id, &path)?;
+            println!(
+                "ingested {} events, {} undecodable",
+                count.events, count.failed
+            );
+        }
+        Command::IngestRpc { url } => {
+            let count = ingest_rpc(&db, &program_id, &url)?;
+            println!(
+                "ingested {} events, {} undecodable",
+                count.events, count.failed
+            );
+        }
+        Command::Pnl { user } => {
+            println!("signer,deposits,bond_value,bond_payout,claimed,staked,unstaked,realized");
+            for p in db.user_pnl(user.as_deref())? {
+                println!(
+                    "{},{},{},{},{},{},{},{}",
+                    p.signer,
+                    p.deposits,
+                    p.bond_value,
+                    p.bond_payout,
+                    p.claimed,
+                    p.staked,
+                    p.unstaked,
+                    p.realized()
+                );
+            }
+        }
+        Command::Volume => {
+            println!("bond,deposits,amount,value,payout,first_time,last_time");
+            for v in db.bond_volume()? {
+                println!(
+                    "{},{},{},{},{},{},{}",
+                    v.bond,
+                    v.deposits,
+                    v.amount,
+                    v.value,
+                    v.payout,
+                    v.first_time.unwrap_or_default(),
+                    v.last_time.unwrap_or_default()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Count {
+    events: usize,
+    // events of the program that match none of their known layouts
+    failed: usize,
+}
+
+impl std::ops::AddAssign for Count {
+    fn add_assign(&mut self, other: Count) {
+        self.events += other.events;
+        self.failed += other.failed;
+    }
+}
+
+// undecodable events are logged and skipped, keeping their index so the
+// rest of the transaction's events don't shift if they're decoded later
+fn ingest(db: &Db, program_id: &Pubkey, tx: &Tx, logs: &[String]) -> Result<Count> {
+    let mut count = Count::default();
+    for (idx, event) in events::parse_logs(program_id, logs).iter().enumerate() {
+        match event {
+            Ok(event) => {
+                db.insert(tx, idx, event)?;
+                count.events += 1;
+            }
+            Err(e) => {
+                eprintln!("{}: skipping event {}: {}", tx.signature, idx, e);
+                count.failed += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+fn ingest_file(db: &Db, program_id: &Pubkey, path: &Path) -> Result<Count> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut count = Count::default();
+    for line in file.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let captured: CapturedTx = serde_json::from_str(&line)?;
+        if captured.err.is_some_and(|e| !e.is_null()) {
+            continue;
+        }
+        let tx = Tx {
+            signature: &captured.signature,
+            slot: captured.slot,
+            block_time: captured.block_time,
+        };
+        count += ingest(db, program_id, &tx, &captured.logs)?;
+    }
+    Ok(count)
+}
+
+fn ingest_rpc(db: &Db, program_id: &Pubkey, url: &str) -> Result<Count> {
+    let rpc = RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed());
+    let program = program_id.to_string();
+    let until = match db.cursor(&program)? {
+        Some(signature) => Some(Signature::from_str(&signature)?),
+        None => None,
+    };
+
+    // signatures come newest first, page back until the cursor
+    let mut signatures = vec![];
+    let mut before = None;
+    loop {
+        let page = rpc.get_signatures_for_address_with_config(
+            program_id,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until,
+                limit: Some(1000),
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )?;
+        match page.last() {
+            Some(last) => before = Some(Signature::from_str(&last.signature)?),
+            None => break,
+        }
+        signatures.extend(page);
+    }
+
+    let mut count = Count::default();
+    for status in signatures.iter().rev() {
+        if status.err.is_none() {
+            let signature = Signature::from_str(&status.signature)?;
+            let tx = rpc.get_transaction(&signature, UiTransactionEncoding::Json)?;
+            let logs = tx
+                .transaction
+                .meta
+                .and_then(|meta| meta.log_messages)
+                .unwrap_or_default();
+            let tx = Tx {
+                signature: &status.signature,
+                slot: status.slot,
+                block_time: status.block_time,
+            };
+            count += ingest(db, program_id, &tx, &logs)?;
+        }
+        db.set_cursor(&program, &status.signature)?;
+    }
+    Ok(count)
+}
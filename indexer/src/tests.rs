@@ -0,0 +1,184 @@
+use crate::db::Db;
+use crate::events::{decode, parse_logs, Event};
+use crate::{ingest_file, CapturedTx, Count};
+use anchor_lang::prelude::Pubkey;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const PROGRAM_ID: &str = "6SMGNVogDVutJ8TpuLkyKUA8aWMbe8xpH5nC9ADw2PXB";
+
+fn program_id() -> Pubkey {
+    Pubkey::from_str(PROGRAM_ID).unwrap()
+}
+
+fn key(byte: u8) -> String {
+    Pubkey::new_from_array([byte; 32]).to_string()
+}
+
+fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name)
+}
+
+fn fixture(name: &str) -> Vec<CapturedTx> {
+    std::fs::read_to_string(fixture_path(name))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn ingest_fixture(db: &Db, name: &str) -> Count {
+    ingest_file(db, &program_id(), &fixture_path(name)).unwrap()
+}
+
+#[test]
+fn parse_logs_decodes_current_layouts() {
+    let txs = fixture("logs.jsonl");
+    let events: Vec<Event> = parse_logs(&program_id(), &txs[0].logs)
+        .into_iter()
+        .map(|e| e.unwrap())
+        .collect();
+    assert_eq!(events.len(), 1);
+    match &events[0] {
+        Event::BondDeposit(e) => {
+            assert_eq!(e.signer.to_string(), key(1));
+            assert_eq!(e.bond.to_string(), key(3));
+            assert_eq!(e.amount, 300_000_000);
+            assert_eq!(e.price, 1_000_000_000);
+            assert_eq!(e.payout, 300_000_000_000);
+            assert_eq!(e.referrer, Pubkey::default());
+        }
+        _ => panic!("expected a bond deposit"),
+    }
+
+    let events: Vec<Event> = parse_logs(&program_id(), &txs[1].logs)
+        .into_iter()
+        .map(|e| e.unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    match &events[0] {
+        Event::StakingRebase(e) => {
+            assert_eq!(e.amount, 1_000_000);
+            assert_eq!(e.pool, 300_001_000_000);
+            assert_eq!(e.index, 1_000_003_333);
+        }
+        _ => panic!("expected a rebase"),
+    }
+    assert!(matches!(events[1], Event::BondWithdraw(_)));
+}
+
+#[test]
+fn parse_logs_decodes_legacy_layouts() {
+    let txs = fixture("logs_legacy.jsonl");
+
+    // the deposit logged by the invoked token program isn't ours
+    let events = parse_logs(&program_id(), &txs[0].logs);
+    assert_eq!(events.len(), 1);
+    match events[0].as_ref().unwrap() {
+        Event::BondDeposit(e) => {
+            assert_eq!(e.amount, 200_000_000);
+            assert_eq!(e.payout, 200_000_000_000);
+            assert_eq!(e.referrer, Pubkey::default());
+            assert_eq!(e.referrer_fee, 0);
+        }
+        _ => panic!("expected a bond deposit"),
+    }
+
+    let events = parse_logs(&program_id(), &txs[1].logs);
+    match events[0].as_ref().unwrap() {
+        Event::StakingRebase(e) => {
+            assert_eq!((e.epoch, e.amount, e.pool), (0, 500_000, 100_000_000_000));
+            assert_eq!((e.index, e.boost), (0, 0));
+        }
+        _ => panic!("expected a rebase"),
+    }
+
+    let events = parse_logs(&program_id(), &txs[2].logs);
+    assert_eq!(events.len(), 2);
+    match events[0].as_ref().unwrap() {
+        Event::StakingRebase(e) => {
+            assert_eq!((e.epoch, e.amount, e.index), (3, 700_000, 1_000_001_000));
+            assert_eq!(e.boost, 0);
+        }
+        _ => panic!("expected a rebase"),
+    }
+    assert!(events[1].is_err());
+}
+
+#[test]
+fn decode_ignores_other_data() {
+    assert!(decode(&[]).unwrap().is_none());
+    assert!(decode(&[0; 64]).unwrap().is_none());
+}
+
+#[test]
+fn ingest_file_counts_events() {
+    let db = Db::open(Path::new(":memory:")).unwrap();
+    // the last transaction failed and is skipped
+    let count = ingest_fixture(&db, "logs.jsonl");
+    assert_eq!(
+        count,
+        Count {
+            events: 5,
+            failed: 0
+        }
+    );
+    let count = ingest_fixture(&db, "logs_legacy.jsonl");
+    assert_eq!(
+        count,
+        Count {
+            events: 3,
+            failed: 1
+        }
+    );
+}
+
+#[test]
+fn ingest_file_is_idempotent() {
+    let db = Db::open(Path::new(":memory:")).unwrap();
+    ingest_fixture(&db, "logs.jsonl");
+    ingest_fixture(&db, "logs.jsonl");
+    let pnl = db.user_pnl(None).unwrap();
+    assert_eq!(pnl.len(), 1);
+    assert_eq!(pnl[0].deposits, 1);
+}
+
+#[test]
+fn user_pnl() {
+    let db = Db::open(Path::new(":memory:")).unwrap();
+    ingest_fixture(&db, "logs.jsonl");
+    ingest_fixture(&db, "logs_legacy.jsonl");
+
+    let pnl = db.user_pnl(Some(&key(1))).unwrap();
+    assert_eq!(pnl.len(), 1);
+    let p = &pnl[0];
+    assert_eq!(p.signer, key(1));
+    assert_eq!(p.deposits, 2);
+    assert_eq!(p.bond_value, 500_000_000_000);
+    assert_eq!(p.bond_payout, 500_000_000_000);
+    assert_eq!(p.claimed, 50_000_000_000);
+    assert_eq!((p.staked, p.unstaked), (1_000, 1_000));
+    assert_eq!(p.realized(), -500_000_000_000);
+
+    assert!(db.user_pnl(Some(&key(9))).unwrap().is_empty());
+}
+
+#[test]
+fn bond_volume() {
+    let db = Db::open(Path::new(":memory:")).unwrap();
+    ingest_fixture(&db, "logs.jsonl");
+    ingest_fixture(&db, "logs_legacy.jsonl");
+
+    let volume = db.bond_volume().unwrap();
+    assert_eq!(volume.len(), 1);
+    let v = &volume[0];
+    assert_eq!(v.bond, key(3));
+    assert_eq!(v.deposits, 2);
+    assert_eq!(v.amount, 500_000_000);
+    assert_eq!(v.value, 500_000_000_000);
+    assert_eq!(v.payout, 500_000_000_000);
+    assert_eq!(v.first_time, Some(1_630_000_000));
+    assert_eq!(v.last_time, Some(1_640_000_000));
+}
@@ -1,10 +1,23 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::{invoke_signed, set_return_data};
+use anchor_lang::AccountsClose;
 use anchor_spl::token::{self, Mint, Token, TokenAccount};
+use spl_token::instruction::AuthorityType;
+use std::cell::{Ref, RefMut};
 use std::mem::size_of;
 
+pub mod math;
+use math::*;
+
 declare_id!("6SMGNVogDVutJ8TpuLkyKUA8aWMbe8xpH5nC9ADw2PXB");
 
-const ONE: u64 = 10_u64.pow(9);
+// metaplex token metadata program
+pub mod token_metadata {
+    use super::*;
+    declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
 
 #[error]
 pub enum ErrorCode {
@@ -28,8 +41,103 @@ pub enum ErrorCode {
     BondPriceSlipped,
     #[msg("no space for more bonds")]
     UserNoSpaceForMoreBonds,
+    #[msg("nothing to claim")]
+    NothingToClaim,
+    #[msg("staking warmup not over")]
+    StakingWarmup,
+    #[msg("staking cooldown not over")]
+    StakingCooldown,
+    #[msg("lock duration invalid")]
+    LockDurationInvalid,
+    #[msg("lock not over")]
+    LockNotOver,
+    #[msg("governance disabled")]
+    GovernanceDisabled,
+    #[msg("proposal voting over")]
+    ProposalVotingOver,
+    #[msg("proposal voting not over")]
+    ProposalVotingNotOver,
+    #[msg("proposal rejected")]
+    ProposalRejected,
+    #[msg("proposal already executed")]
+    ProposalExecuted,
+    #[msg("lock not eligible to vote")]
+    LockNotEligible,
+    #[msg("gauges disabled")]
+    GaugesDisabled,
+    #[msg("bond paused")]
+    BondPaused,
+    #[msg("not a multisig owner")]
+    MultisigNotOwner,
+    #[msg("multisig owners changed")]
+    MultisigOwnersChanged,
+    #[msg("multisig threshold not reached")]
+    MultisigThresholdNotReached,
+    #[msg("multisig transaction already executed")]
+    MultisigTransactionExecuted,
+    #[msg("market capacity exceeded")]
+    MarketCapacityExceeded,
+    #[msg("bond user at max debt")]
+    BondUserAtMaxDebt,
+    #[msg("bond at max payout for this slot")]
+    BondSlotAtMaxPayout,
+    #[msg("wallet not allowlisted for bond")]
+    BondNotAllowlisted,
+    #[msg("bond allowance exceeded")]
+    BondAllowanceExceeded,
+    #[msg("early exits disabled")]
+    ExitDisabled,
+    #[msg("user has bonds or stakes pending")]
+    UserNotEmpty,
+    #[msg("unsupported account version, migrate it first")]
+    AccountVersion,
+    #[msg("not enough bond price history for window")]
+    BondTwapWindow,
+    #[msg("oracle price stale")]
+    OracleStale,
+    #[msg("bond priced above market")]
+    BondAboveMarket,
+    #[msg("bond priced below backing")]
+    BondBelowBacking,
+    #[msg("bond position tokenized")]
+    BondTokenized,
+    #[msg("locks ended in lock curve period need a refresh")]
+    LockCurvePeriodUnsettled,
 }
 
+pub const PROPOSAL_MAX_ACTIONS: usize = 8;
+pub const MULTISIG_MAX_OWNERS: usize = 10;
+pub const FEE_SPLIT_MAX_RECIPIENTS: usize = 8;
+pub const BOND_OBSERVATIONS: usize = 32;
+// seconds between BondObservations entries, so that filling the history
+// takes at least BOND_OBSERVATIONS of them
+pub const BOND_OBSERVATION_INTERVAL: u64 = 30;
+// LockCurve.periods, locks can end at most LOCK_CURVE_HORIZON of them ahead
+pub const LOCK_CURVE_PERIODS: usize = 128;
+pub const LOCK_CURVE_HORIZON: u64 = 32;
+
+// Oracle.kind
+// constant product pool of the reserve token against an rfv stablecoin
+pub const ORACLE_POOL: u64 = 0;
+// PriceFeed account
+pub const ORACLE_FEED: u64 = 1;
+// FeeSplitRecipient.weight total
+pub const FEE_SPLIT_BPS: u64 = 10_000;
+// current layout versions, bumped along with a migrate_* upgrade
+pub const TREASURY_VERSION: u8 = 1;
+pub const BOND_VERSION: u8 = 1;
+pub const USER_VERSION: u8 = 1;
+
+// Role.roles bits
+// create and configure bonds
+pub const ROLE_BOND_MANAGER: u64 = 1;
+// lower bond limits
+pub const ROLE_RISK_MANAGER: u64 = 1 << 1;
+// move reserves out of treasury token accounts
+pub const ROLE_TREASURER: u64 = 1 << 2;
+// pause bonds
+pub const ROLE_GUARDIAN: u64 = 1 << 3;
+
 #[account(zero_copy)]
 pub struct Treasury {
     pub key: Pubkey,
@@ -42,13 +150,98 @@ pub struct Treasury {
     pub authority: Pubkey,
     // total rfv bonded
     pub total_reserves: u64,
-    // 1e9 percent of reserves to mint to stakers per day
+    // 1e9 percent of reserve supply to distribute to stakers per epoch
     pub staking_rate: u64,
-    // last time staking rewards were minted
+    // last time an epoch ended and staking rewards were minted
     pub staking_last: u64,
+    // seconds staking deposits wait before minting staking tokens
+    pub staking_warmup: u64,
+    // seconds unstaked reserve tokens wait before being redeemable
+    pub staking_cooldown: u64,
+    // reserve tokens in token_reserve_staking warming up or cooling down,
+    // not backing staking tokens
+    pub staking_warmup_total: u64,
+    pub staking_cooldown_total: u64,
+    // longest lock duration in seconds, locks are disabled when 0
+    pub lock_max_duration: u64,
+    // 1e9 percent of each epoch's distribution going to locks, by weight
+    pub lock_boost_rate: u64,
+    // min lock voting weight cast for a proposal to pass, governance is
+    // disabled when 0
+    pub governance_quorum: u64,
+    // 1e9 percent of weight cast that must be in favor
+    pub governance_threshold: u64,
+    // layout version, TREASURY_VERSION once migrated
+    pub version: u8,
     _reserved: [u64; 8],
 }
 
+impl Treasury {
+    // reserve tokens backing staking tokens
+    pub fn staking_pool(&self, token_reserve_staking_amount: u64) -> u64 {
+        token_reserve_staking_amount
+            .saturating_sub(self.staking_warmup_total)
+            .saturating_sub(self.staking_cooldown_total)
+    }
+}
+
+// zero copy accounts carrying a layout version
+pub trait Versioned: anchor_lang::ZeroCopy + Owner {
+    const VERSION: u8;
+    fn version(&self) -> u8;
+}
+
+impl Versioned for Treasury {
+    const VERSION: u8 = TREASURY_VERSION;
+    fn version(&self) -> u8 {
+        self.version
+    }
+}
+
+impl Versioned for Bond {
+    const VERSION: u8 = BOND_VERSION;
+    fn version(&self) -> u8 {
+        self.version
+    }
+}
+
+impl Versioned for User {
+    const VERSION: u8 = USER_VERSION;
+    fn version(&self) -> u8 {
+        self.version
+    }
+}
+
+// AccountLoader::load only checks the discriminator, an account created
+// before its layout changed would be read at the wrong offsets (or fail
+// as too small), these also reject it until it's migrated
+pub trait LoadVersioned<T> {
+    fn load_versioned(&self) -> Result<Ref<'_, T>>;
+    fn load_mut_versioned(&self) -> Result<RefMut<'_, T>>;
+}
+
+impl<'info, T: Versioned> LoadVersioned<T> for AccountLoader<'info, T> {
+    fn load_versioned(&self) -> Result<Ref<'_, T>> {
+        require!(
+            self.to_account_info().data_len() == 8 + size_of::<T>(),
+            ErrorCode::AccountVersion
+        );
+        let account = self.load()?;
+        require!(account.version() == T::VERSION, ErrorCode::AccountVersion);
+        Ok(account)
+    }
+
+    fn load_mut_versioned(&self) -> Result<RefMut<'_, T>> {
+        require!(
+            self.to_account_info().data_len() == 8 + size_of::<T>(),
+            ErrorCode::AccountVersion
+        );
+        let account = self.load_mut()?;
+        require!(account.version() == T::VERSION, ErrorCode::AccountVersion);
+        Ok(account)
+    }
+}
+
 #[account(zero_copy)]
 pub struct Bond {
     pub bump: u8,
@@ -72,16 +265,507 @@ pub struct Bond {
     pub total_debt_last: u64,
     // all time rfv bonded
     pub total_debt_alltime: u64,
+    // epoch gauge_votes were cast in, and votes of the epoch before it
+    pub gauge_epoch: u64,
+    pub gauge_votes: u64,
+    pub gauge_votes_last: u64,
+    // time deposits were paused at, 0 when not paused
+    pub paused: u64,
+    // max rfv per user per vesting_period, 0 for no limit
+    pub max_debt_user: u64,
+    // max payout per slot across users, 0 for no limit
+    pub max_payout_slot: u64,
+    // payout of deposits made in slot
+    pub slot: u64,
+    pub slot_payout: u64,
+    // layout version, BOND_VERSION once migrated
+    pub version: u8,
+    // 1e9 percent of the unvested staking tokens of a deposit forfeited
+    // when exiting it early, early exits are disabled when 0
+    pub exit_penalty: u64,
+    _reserved: [u64; 7],
+}
+
+// merkle root of (wallet, max_amount) leaves allowed to deposit into a bond
+#[account(zero_copy)]
+pub struct BondAllowlist {
+    pub bump: u8,
+    pub bond: Pubkey,
+    // zeroed to let anyone deposit again
+    pub root: [u8; 32],
+    _reserved: [u64; 4],
+}
+
+// how deposits into a bond vest, linearly when it doesn't exist
+#[account(zero_copy)]
+pub struct BondSchedule {
+    pub bump: u8,
+    pub bond: Pubkey,
+    // one of the VESTING_ kinds
+    pub kind: u64,
+    pub cliff: u64,
+    _reserved: [u64; 4],
+}
+
+// last BOND_OBSERVATIONS prices of a bond, the oldest is overwritten first
+#[account(zero_copy)]
+pub struct BondObservations {
+    pub bump: u8,
+    pub bond: Pubkey,
+    // of the latest observation
+    pub index: u64,
+    pub count: u64,
+    pub observations: [BondObservation; BOND_OBSERVATIONS],
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct BondObservation {
+    pub time: u64,
+    pub price: u64,
+    pub debt_ratio: u64,
+    // sum of price * seconds up to `time`, prices holding until the next
+    // observation
+    pub price_cumulative: u128,
+}
+
+// market price of the reserve token, once it exists bonds are only sold
+// below it and above backing per token
+#[account(zero_copy)]
+pub struct Oracle {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    // one of the ORACLE_ kinds
+    pub kind: u64,
+    // the pool's reserve and quote token accounts, or a PriceFeed (and
+    // source_quote unused)
+    pub source: Pubkey,
+    pub source_quote: Pubkey,
+    pub quote_decimals: u64,
+    // seconds a price can be used for by deposits
+    pub max_age: u64,
+    // rfv per reserve token, as of oracle_update, for pools averaged over
+    // the last window
+    pub price: u64,
+    pub updated: u64,
+    // ORACLE_POOL: seconds a price is averaged over, so a pool moved within
+    // a transaction (or for less than a window) barely moves it
+    pub window: u64,
+    // ORACLE_POOL: last pool price read and when, the sum of pool prices
+    // weighted by how long they held, and its value when the window started
+    pub spot: u64,
+    pub spot_updated: u64,
+    pub price_cumulative: u128,
+    pub window_start: u64,
+    pub window_cumulative: u128,
+    // ORACLE_POOL: min seconds between pool reads, updates sooner leave the
+    // oracle as is, so a pool moved and restored around an update in one
+    // transaction isn't read if it was read recently
+    pub interval: u64,
+    _reserved: [u64; 3],
+}
+
+// price pushed by its authority, for keepers relaying another oracle and
+// for tests
+#[account(zero_copy)]
+pub struct PriceFeed {
+    pub bump: u8,
+    pub authority: Pubkey,
+    // 1e9
+    pub price: u64,
+    pub updated: u64,
+}
+
+// bond tokens an allowlisted wallet deposited so far
+#[account(zero_copy)]
+pub struct BondAllowance {
+    pub bump: u8,
+    pub bond: Pubkey,
+    pub signer: Pubkey,
+    pub used: u64,
+}
+
+#[account(zero_copy)]
+pub struct Epoch {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    pub number: u64,
+    // seconds
+    pub length: u64,
+    pub end: u64,
+    // reserve tokens to distribute to stakers when the current epoch ends
+    pub distribute: u64,
+    // staking_rate moves by adjustment_rate per epoch toward adjustment_target
+    pub adjustment_rate: u64,
+    pub adjustment_target: u64,
+    // staking index at the end of past epochs, by epoch number
+    pub history: [EpochIndex; 64],
+    // rfv of max_debt split across bonds by gauge votes each epoch, gauges
+    // are disabled when 0
+    pub gauge_budget: u64,
+    // lock weight voted on gauges this epoch and the previous one
+    pub gauge_votes: u64,
+    pub gauge_votes_last: u64,
+    _reserved: [u64; 3],
+}
+
+impl Epoch {
+    pub fn record(&mut self, timestamp: u64, index: u64) {
+        let i = (self.number % self.history.len() as u64) as usize;
+        self.history[i] = EpochIndex {
+            number: self.number,
+            timestamp,
+            index,
+        };
+    }
+}
+
+// one per lock and epoch, its existence prevents voting twice
+#[account(zero_copy)]
+pub struct GaugeVote {
+    pub bump: u8,
+    pub lock: Pubkey,
+    pub epoch: u64,
+    pub weight: u64,
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct EpochIndex {
+    pub number: u64,
+    pub timestamp: u64,
+    // reserve tokens per staking token, 1e9
+    pub index: u64,
+}
+
+#[account(zero_copy)]
+pub struct Lock {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    pub signer: Pubkey,
+    // staking tokens locked
+    pub amount: u64,
+    pub start: u64,
+    // a multiple of the lock curve's period
+    pub end: u64,
+    // boost weight slope, see lock_slope
+    pub slope: u128,
+    // lock curve accumulators as of the last update
+    pub reward_per_weight_paid: u128,
+    pub reward_per_weight_time_paid: u128,
+    // staking tokens of boost earned and not yet claimed
+    pub pending: u64,
+    // last time amount or end changed, locks can only vote on proposals
+    // started after it
+    pub updated: u64,
+    // time the lock settled its boost on the lock curve once ended, 0
+    // before
+    pub settled: u64,
+    _reserved: [u64; 2],
+}
+
+// bond market run by a third party issuer, selling its own payout token
+// from the market's vault for a quote token, priced like bonds
+#[account(zero_copy)]
+pub struct Market {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    // base key the market pda is derived from
+    pub key: Pubkey,
+    pub issuer: Pubkey,
+    pub mint_quote: Pubkey,
+    pub mint_payout: Pubkey,
+    // vault holding payout tokens for sale and owed to buyers
+    pub token_payout: Pubkey,
+    // issuer account receiving quote tokens
+    pub token_quote: Pubkey,
+    pub quote_decimals: u8,
+    pub payout_decimals: u8,
+    // same as Bond, max_payout and the debt ratio being relative to the
+    // payout token's supply
+    pub vesting_period: u64,
+    pub rfv_rate: u64,
+    pub min_price: u64,
+    pub max_payout: u64,
+    pub max_debt: u64,
+    pub bcv: u64,
+    pub total_debt: u64,
+    pub total_debt_last: u64,
+    pub total_debt_alltime: u64,
+    // payout tokens sold and not yet claimed
+    pub owed: u64,
     _reserved: [u64; 8],
 }
 
+impl Market {
+    // the market's pricing terms as a bond, for bond_quote
+    pub fn terms(&self) -> Bond {
+        Bond {
+            bump: 0,
+            treasury: self.treasury,
+            mint_bond: self.mint_quote,
+            token_decimals: self.quote_decimals,
+            vesting_period: self.vesting_period,
+            rfv_rate: self.rfv_rate,
+            min_price: self.min_price,
+            max_payout: self.max_payout,
+            max_debt: self.max_debt,
+            fee: 0,
+            bcv: self.bcv,
+            total_debt: self.total_debt,
+            total_debt_last: self.total_debt_last,
+            total_debt_alltime: self.total_debt_alltime,
+            gauge_epoch: 0,
+            gauge_votes: 0,
+            gauge_votes_last: 0,
+            paused: 0,
+            max_debt_user: 0,
+            max_payout_slot: 0,
+            slot: 0,
+            slot_payout: 0,
+            version: BOND_VERSION,
+            exit_penalty: 0,
+            _reserved: [0; 7],
+        }
+    }
+}
+
+// one per market deposit, vesting the payout linearly
+#[account(zero_copy)]
+pub struct MarketPosition {
+    pub bump: u8,
+    pub market: Pubkey,
+    pub signer: Pubkey,
+    pub price: u64,
+    // payout tokens
+    pub payout: u64,
+    pub claimed: u64,
+    pub vesting_start: u64,
+    pub vesting_period: u64,
+}
+
+// protocol fee on third party markets, paid to the treasury
+#[account(zero_copy)]
+pub struct MarketConfig {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    // 1e9 percent of quote tokens deposited
+    pub fee: u64,
+    _reserved: [u64; 4],
+}
+
+// splits fees accruing in token accounts it owns between recipients
+// (meant to be set as the treasury dao)
+#[account(zero_copy)]
+pub struct FeeSplit {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    pub recipients: [FeeSplitRecipient; FEE_SPLIT_MAX_RECIPIENTS],
+    _reserved: [u64; 4],
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct FeeSplitRecipient {
+    // wallet owning the token accounts fees are paid to
+    pub owner: Pubkey,
+    // basis points, 0 for unused slots
+    pub weight: u64,
+}
+
+// frontend earning part of the protocol fee on bond deposits it refers
+#[account(zero_copy)]
+pub struct Referrer {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    pub signer: Pubkey,
+    // reserve token account fees are minted to
+    pub token_reserve: Pubkey,
+    // 1e9 percent of the bond fee, up to fee_max set by the authority
+    pub fee: u64,
+    pub fee_max: u64,
+    // lifetime rfv deposited, fees earned and deposits referred
+    pub volume: u64,
+    pub earned: u64,
+    pub deposits: u64,
+    _reserved: [u64; 4],
+}
+
+// privileges granted to a signer by the treasury authority. Proposals act
+// with the roles granted to the treasury's own address
+#[account(zero_copy)]
+pub struct Role {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    pub signer: Pubkey,
+    // ROLE_* bits
+    pub roles: u64,
+}
+
+impl Role {
+    // whether `signer` holds all of `roles` on `treasury` through it
+    pub fn allows(&self, treasury: &Pubkey, signer: &Pubkey, roles: u64) -> bool {
+        self.treasury == *treasury && self.signer == *signer && self.roles & roles == roles
+    }
+}
+
+impl Lock {
+    // decays linearly to 0 at the end of the lock, from the slope it was
+    // made with so later lock_max_duration changes don't affect it
+    pub fn voting_weight(&self, now: u64) -> Result<u64> {
+        lock_weight(self.slope, self.end, now)
+    }
+}
+
+// boost weight of all locks of a treasury, (bias - slope * t) / 1e9 at `t`.
+// Lock ends are rounded down to a multiple of `period`, the slope of the
+// locks ending at each is kept in `periods` (by period number modulo their
+// count) and taken out of the totals once it's over, recording the reward
+// accumulators as of that end for those locks. An entry is only reused
+// once all of its locks have settled their boost from them
+#[account(zero_copy)]
+pub struct LockCurve {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    // seconds, set from lock_max_duration on creation
+    pub period: u64,
+    // first period number not yet taken out of the totals
+    pub next: u64,
+    pub slope: u128,
+    pub bias: u128,
+    // sums over rebases of boost * 1e18 / weight, and of that times the
+    // rebase time, wrapping
+    pub reward_per_weight: u128,
+    pub reward_per_weight_time: u128,
+    pub periods: [LockCurvePeriod; LOCK_CURVE_PERIODS],
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct LockCurvePeriod {
+    pub number: u64,
+    // of the locks ending at number * period, until then
+    pub slope: u128,
+    // locks ending at number * period that haven't settled since
+    pub locks: u64,
+    // LockCurve accumulators as of number * period, once over
+    pub reward_per_weight: u128,
+    pub reward_per_weight_time: u128,
+}
+
+impl LockCurve {
+    // takes the locks ending by `now` out of the totals
+    pub fn checkpoint(&mut self, now: u64) {
+        let due = now / self.period;
+        if due < self.next {
+            return;
+        }
+        let len = LOCK_CURVE_PERIODS as u64;
+        for number in self.next.max((due + 1).saturating_sub(len))..=due {
+            let p = &mut self.periods[(number % len) as usize];
+            if p.number >= self.next && p.number <= due {
+                self.slope -= p.slope;
+                self.bias -= p.slope * (p.number * self.period) as u128;
+                p.slope = 0;
+                p.reward_per_weight = self.reward_per_weight;
+                p.reward_per_weight_time = self.reward_per_weight_time;
+            }
+        }
+        self.next = due + 1;
+    }
+
+    // total weight at `now` (1e9), once checkpointed
+    pub fn weight(&self, now: u64) -> u128 {
+        self.bias - self.slope * now as u128
+    }
+
+    // shares `boost` between the locks by their weight at `now`
+    pub fn reward(&mut self, boost: u64, weight: u128, now: u64) {
+        let per_weight = boost as u128 * (ONE as u128 * ONE as u128) / weight;
+        self.reward_per_weight = self.reward_per_weight.wrapping_add(per_weight);
+        self.reward_per_weight_time = self
+            .reward_per_weight_time
+            .wrapping_add(per_weight.wrapping_mul(now as u128));
+    }
+
+    pub fn ended(&self, end: u64) -> bool {
+        end / self.period < self.next
+    }
+
+    // accumulators as of `end`, a period that's over, for a lock ending
+    // then that hasn't settled
+    pub fn accumulators_at(&self, end: u64) -> (u128, u128) {
+        let number = end / self.period;
+        let p = self.periods[(number % LOCK_CURVE_PERIODS as u64) as usize];
+        (p.reward_per_weight, p.reward_per_weight_time)
+    }
+
+    // reusing the entry of a period, at the earliest LOCK_CURVE_PERIODS -
+    // LOCK_CURVE_HORIZON periods after it, once its locks have settled
+    pub fn add(&mut self, slope: u128, end: u64) -> ProgramResult {
+        let number = end / self.period;
+        let p = &mut self.periods[(number % LOCK_CURVE_PERIODS as u64) as usize];
+        if p.number != number {
+            require!(p.locks == 0, ErrorCode::LockCurvePeriodUnsettled);
+            *p = LockCurvePeriod {
+                number,
+                ..LockCurvePeriod::default()
+            };
+        }
+        p.slope += slope;
+        p.locks += 1;
+        self.slope += slope;
+        self.bias += slope * end as u128;
+        Ok(())
+    }
+
+    // of a lock that hasn't ended
+    pub fn remove(&mut self, slope: u128, end: u64) {
+        let number = end / self.period;
+        let p = &mut self.periods[(number % LOCK_CURVE_PERIODS as u64) as usize];
+        p.slope -= slope;
+        p.locks -= 1;
+        self.slope -= slope;
+        self.bias -= slope * end as u128;
+    }
+
+    // of a lock that has ended, once it took its boost up to its end
+    pub fn settle(&mut self, end: u64) {
+        let number = end / self.period;
+        self.periods[(number % LOCK_CURVE_PERIODS as u64) as usize].locks -= 1;
+    }
+}
+
 #[account(zero_copy)]
 pub struct User {
     pub bump: u8,
     pub signer: Pubkey,
     pub treasury: Pubkey,
     pub bonds: [UserBond; 10],
-    _reserved: [u64; 8],
+    // reserve tokens deposited, to be staked after warmup_end
+    pub warmup_amount: u64,
+    pub warmup_end: u64,
+    // reserve tokens unstaked, to be redeemed after cooldown_end
+    pub cooldown_amount: u64,
+    pub cooldown_end: u64,
+    // layout version, USER_VERSION once migrated
+    pub version: u8,
+    // rfv of the user's bond deposits, decaying like Bond.total_debt over
+    // the vesting period of the bond deposited into. held against
+    // max_debt_user, exits and withdrawals don't lower it
+    pub debt: u64,
+    pub debt_last: u64,
+    _reserved: [u8; 15],
+}
+
+// a UserBond moved out of its User, claimable by whoever holds the only
+// token of `mint`
+#[account(zero_copy)]
+pub struct BondPosition {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    pub mint: Pubkey,
+    pub bond: UserBond,
 }
 
 #[zero_copy]
@@ -98,7 +782,207 @@ pub struct UserBond {
     pub claimed: u64,
     pub vesting_start: u64,
     pub vesting_period: u64,
-    _reserved: [u64; 2],
+    // snapshot of the bond's BondSchedule
+    pub vesting_kind: u64,
+    pub vesting_cliff: u64,
+}
+
+impl UserBond {
+    // left behind by bond_tokenize, still charging the user the bond's debt
+    // (and a slot) until it would have vested
+    pub fn tokenized(&self) -> bool {
+        self.staked == 0
+    }
+
+    pub fn schedule(&self) -> VestingSchedule {
+        VestingSchedule {
+            kind: self.vesting_kind,
+            start: self.vesting_start,
+            period: self.vesting_period,
+            cliff: self.vesting_cliff,
+        }
+    }
+
+    // claims what vested since the last claim, and whether the bond is done
+    pub fn claim(&mut self, now: u64) -> Result<(u64, bool)> {
+        let vested = vested_amount(&self.schedule(), self.staked, now)?;
+        let amount = vested.saturating_sub(self.claimed);
+        self.claimed += amount;
+        let done = self.claimed == self.staked && now >= self.vesting_start + self.vesting_period;
+        Ok((amount, done))
+    }
+}
+
+// config changes voted on by locks, applied with proposal_execute once
+// voting ends
+#[account]
+pub struct Proposal {
+    pub bump: u8,
+    pub treasury: Pubkey,
+    pub proposer: Pubkey,
+    pub start: u64,
+    // end of voting
+    pub end: u64,
+    // treasury governance parameters when the proposal started
+    pub quorum: u64,
+    pub threshold: u64,
+    // lock voting weight cast
+    pub votes_for: u64,
+    pub votes_against: u64,
+    pub executed: bool,
+    pub actions: Vec<ProposalAction>,
+}
+
+// one per proposal and lock, its existence prevents voting twice
+#[account(zero_copy)]
+pub struct ProposalVote {
+    pub bump: u8,
+    pub proposal: Pubkey,
+    pub lock: Pubkey,
+    pub weight: u64,
+    pub support: u8,
+}
+
+// same arguments as the authority instructions
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum ProposalAction {
+    TreasuryConfigure { params: TreasuryParams },
+    EpochConfigure { params: EpochParams },
+    // the bond is passed in remaining accounts on execution
+    BondConfigure { bond: Pubkey, params: BondParams },
+    GovernanceConfigure { params: GovernanceParams },
+}
+
+// token metadata program instruction args, borsh encoded after their
+// MetadataInstruction variant index
+const METADATA_UPDATE_V2: u8 = 15;
+const METADATA_CREATE_V3: u8 = 33;
+
+// creators, collection, uses and collection_details are always None, which
+// borsh encodes as a single 0 byte whatever the type
+#[derive(AnchorSerialize)]
+struct MetadataDataV2 {
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    creators: Option<()>,
+    collection: Option<()>,
+    uses: Option<()>,
+}
+
+#[derive(AnchorSerialize)]
+struct MetadataCreateV3 {
+    data: MetadataDataV2,
+    is_mutable: bool,
+    collection_details: Option<()>,
+}
+
+#[derive(AnchorSerialize)]
+struct MetadataUpdateV2 {
+    data: Option<MetadataDataV2>,
+    update_authority: Option<Pubkey>,
+    primary_sale_happened: Option<bool>,
+    is_mutable: Option<bool>,
+}
+
+// protocol_metrics return data, 1e9 unless noted
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct ProtocolMetrics {
+    // total_reserves per reserve token
+    pub backing_per_token: u64,
+    // reserve tokens per staking token
+    pub staking_index: u64,
+    // growth of the staking index over the current epoch (before the
+    // share going to locks)
+    pub reward_rate: u64,
+    // 1e9 percent gained compounding reward_rate over 5 days and a year
+    pub roi_five_day: u64,
+    pub apy: u64,
+    // whole days until compounding staked tokens outgrows total_reserves,
+    // u64::MAX when rewards are 0
+    pub runway_days: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct TreasuryParams {
+    pub dao: Pubkey,
+    pub authority: Pubkey,
+    pub staking_rate: u64,
+    pub staking_warmup: u64,
+    pub staking_cooldown: u64,
+    pub lock_max_duration: u64,
+    pub lock_boost_rate: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct EpochParams {
+    pub length: u64,
+    pub adjustment_rate: u64,
+    pub adjustment_target: u64,
+    pub gauge_budget: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct BondParams {
+    pub vesting_period: u64,
+    pub rfv_rate: u64,
+    pub min_price: u64,
+    pub max_payout: u64,
+    pub max_debt: u64,
+    pub fee: u64,
+    pub bcv: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct GovernanceParams {
+    pub quorum: u64,
+    pub threshold: u64,
+}
+
+// an account's data while it's recreated at its current size, solana 1.8
+// has no way to grow an account in place
+#[account]
+pub struct Migration {
+    pub bump: u8,
+    // account being migrated
+    pub account: Pubkey,
+    // its data past the discriminator
+    pub data: Vec<u8>,
+}
+
+// M of N owners approving reserve program instructions, signed by the
+// multisig_signer pda (meant to be set as the treasury authority)
+#[account]
+pub struct Multisig {
+    pub bump: u8,
+    pub signer_bump: u8,
+    pub treasury: Pubkey,
+    pub threshold: u64,
+    pub owners: Vec<Pubkey>,
+    // incremented when owners change, invalidating pending transactions
+    pub owner_set_seqno: u32,
+}
+
+#[account]
+pub struct MultisigTransaction {
+    pub bump: u8,
+    pub multisig: Pubkey,
+    pub proposer: Pubkey,
+    // reserve program instruction to invoke
+    pub metas: Vec<MultisigAccount>,
+    pub data: Vec<u8>,
+    // by owner index
+    pub approvals: Vec<bool>,
+    pub owner_set_seqno: u32,
+    pub executed: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct MultisigAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
 }
 
 #[event]
@@ -115,6 +999,9 @@ pub struct EventBondDeposit {
     pub price: u64,
     pub payout: u64,
     pub staked: u64,
+    // default pubkey when none
+    pub referrer: Pubkey,
+    pub referrer_fee: u64,
 }
 
 #[event]
@@ -132,17 +1019,22 @@ pub struct EventBondWithdraw {
 }
 
 #[event]
-pub struct EventStakingDeposit {
+pub struct EventBondExit {
     #[index]
     pub signer: Pubkey,
     #[index]
     pub treasury: Pubkey,
-    pub staked: u64,
+    #[index]
+    pub bond: Pubkey,
+    #[index]
+    pub user: Pubkey,
+    // staking tokens released, and burned
     pub amount: u64,
+    pub penalty: u64,
 }
 
 #[event]
-pub struct EventStakingWithdraw {
+pub struct EventStakingDeposit {
     #[index]
     pub signer: Pubkey,
     #[index]
@@ -151,6 +1043,59 @@ pub struct EventStakingWithdraw {
     pub amount: u64,
 }
 
+#[event]
+pub struct EventStakingWithdraw {
+    #[index]
+    pub signer: Pubkey,
+    #[index]
+    pub treasury: Pubkey,
+    pub staked: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct EventStakingRebase {
+    #[index]
+    pub treasury: Pubkey,
+    pub epoch: u64,
+    pub amount: u64,
+    pub pool: u64,
+    pub index: u64,
+    // part of amount going to locks
+    pub boost: u64,
+}
+
+#[event]
+pub struct EventLockDeposit {
+    #[index]
+    pub signer: Pubkey,
+    #[index]
+    pub treasury: Pubkey,
+    pub amount: u64,
+    pub end: u64,
+    pub weight: u64,
+}
+
+#[event]
+pub struct EventLockWithdraw {
+    #[index]
+    pub signer: Pubkey,
+    #[index]
+    pub treasury: Pubkey,
+    pub amount: u64,
+    pub boost: u64,
+}
+
+#[event]
+pub struct EventFeeDistribute {
+    #[index]
+    pub treasury: Pubkey,
+    #[index]
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
 #[program]
 pub mod reserve {
     use super::*;
@@ -278,636 +1223,4042 @@ pub mod reserve {
         treasury.token_staking_vesting = ctx.accounts.token_staking_vesting.key();
         treasury.dao = ctx.accounts.dao.key();
         treasury.authority = ctx.accounts.signer.key();
+        treasury.version = TREASURY_VERSION;
         Ok(())
     }
 
     #[derive(Accounts)]
     pub struct TreasuryConfigure<'info> {
-        #[account(constraint = signer.key() == treasury.load()?.authority @ ErrorCode::Unauthorized)]
+        #[account(constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
         pub signer: Signer<'info>,
         #[account(mut)]
         pub treasury: AccountLoader<'info, Treasury>,
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn treasury_configure(
         ctx: Context<TreasuryConfigure>,
         dao: Pubkey,
         authority: Pubkey,
         staking_rate: u64,
+        staking_warmup: u64,
+        staking_cooldown: u64,
+        lock_max_duration: u64,
+        lock_boost_rate: u64,
     ) -> ProgramResult {
-        let treasury = &mut ctx.accounts.treasury.load_mut()?;
-        treasury.dao = dao;
-        treasury.authority = authority;
-        treasury.staking_rate = staking_rate;
-        Ok(())
+        treasury_apply(
+            &mut *ctx.accounts.treasury.load_mut_versioned()?,
+            &TreasuryParams {
+                dao,
+                authority,
+                staking_rate,
+                staking_warmup,
+                staking_cooldown,
+                lock_max_duration,
+                lock_boost_rate,
+            },
+        )
+    }
+
+    #[derive(Accounts)]
+    pub struct GovernanceConfigure<'info> {
+        #[account(constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub treasury: AccountLoader<'info, Treasury>,
+    }
+
+    pub fn governance_configure(
+        ctx: Context<GovernanceConfigure>,
+        quorum: u64,
+        threshold: u64,
+    ) -> ProgramResult {
+        governance_apply(
+            &mut *ctx.accounts.treasury.load_mut_versioned()?,
+            &GovernanceParams { quorum, threshold },
+        )
     }
 
     #[derive(Accounts)]
     #[instruction(bump: u8)]
-    pub struct UserInitialize<'info> {
+    pub struct EpochInitialize<'info> {
+        #[account(mut, constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
         pub signer: Signer<'info>,
         pub treasury: AccountLoader<'info, Treasury>,
         #[account(
             init,
             payer = signer,
-            seeds = [b"user", treasury.key().as_ref(), signer.key().as_ref()],
+            seeds = [b"epoch", treasury.key().as_ref()],
             bump = bump,
-            space = 8 + size_of::<User>(),
+            space = 8 + size_of::<Epoch>(),
         )]
-        pub user: AccountLoader<'info, User>,
+        pub epoch: AccountLoader<'info, Epoch>,
         pub system_program: Program<'info, System>,
     }
 
-    pub fn user_initialize(ctx: Context<UserInitialize>, bump: u8) -> ProgramResult {
-        let user = &mut ctx.accounts.user.load_init()?;
-        user.treasury = ctx.accounts.treasury.key();
-        user.signer = ctx.accounts.signer.key();
-        user.bump = bump;
+    // the first epoch distributes nothing, the distribution for the
+    // following one is computed when it ends
+    pub fn epoch_initialize(
+        ctx: Context<EpochInitialize>,
+        bump: u8,
+        length: u64,
+        end: u64,
+    ) -> ProgramResult {
+        require!(length > 0, ErrorCode::InvalidParameter);
+        let epoch = &mut ctx.accounts.epoch.load_init()?;
+        epoch.bump = bump;
+        epoch.treasury = ctx.accounts.treasury.key();
+        epoch.length = length;
+        epoch.end = end;
         Ok(())
     }
 
+    #[derive(Accounts)]
+    pub struct EpochConfigure<'info> {
+        #[account(constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+    }
+
+    pub fn epoch_configure(
+        ctx: Context<EpochConfigure>,
+        length: u64,
+        adjustment_rate: u64,
+        adjustment_target: u64,
+        gauge_budget: u64,
+    ) -> ProgramResult {
+        epoch_apply(
+            &mut *ctx.accounts.epoch.load_mut()?,
+            &EpochParams {
+                length,
+                adjustment_rate,
+                adjustment_target,
+                gauge_budget,
+            },
+        )
+    }
+
     #[derive(Accounts)]
     #[instruction(bump: u8)]
-    pub struct BondInitialize<'info> {
-        #[account(constraint = signer.key() == treasury.load()?.authority @ ErrorCode::Unauthorized)]
+    pub struct RoleInitialize<'info> {
+        #[account(mut, constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
         pub signer: Signer<'info>,
         pub treasury: AccountLoader<'info, Treasury>,
+        pub member: AccountInfo<'info>,
         #[account(
             init,
             payer = signer,
-            seeds = [b"bond", treasury.key().as_ref(), mint_bond.key().as_ref()],
+            seeds = [b"role", treasury.key().as_ref(), member.key().as_ref()],
             bump = bump,
-            space = 8 + size_of::<Bond>(),
+            space = 8 + size_of::<Role>(),
         )]
-        pub bond: AccountLoader<'info, Bond>,
-        pub mint_bond: Account<'info, Mint>,
+        pub role: AccountLoader<'info, Role>,
         pub system_program: Program<'info, System>,
     }
 
-    pub fn bond_initialize(ctx: Context<BondInitialize>, _bump: u8) -> ProgramResult {
-        let bond = &mut ctx.accounts.bond.load_init()?;
-        bond.treasury = ctx.accounts.treasury.key();
-        bond.mint_bond = ctx.accounts.mint_bond.key();
-        bond.token_decimals = ctx.accounts.mint_bond.decimals;
-        bond.total_debt_last = unix_now()?;
+    pub fn role_initialize(ctx: Context<RoleInitialize>, bump: u8) -> ProgramResult {
+        let role = &mut ctx.accounts.role.load_init()?;
+        role.bump = bump;
+        role.treasury = ctx.accounts.treasury.key();
+        role.signer = ctx.accounts.member.key();
         Ok(())
     }
 
     #[derive(Accounts)]
-    pub struct BondConfigure<'info> {
-        #[account(constraint = signer.key() == treasury.load()?.authority @ ErrorCode::Unauthorized)]
+    pub struct RoleConfigure<'info> {
+        #[account(constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub role: AccountLoader<'info, Role>,
+    }
+
+    // adds the ROLE_* bits in `roles`
+    pub fn role_grant(ctx: Context<RoleConfigure>, roles: u64) -> ProgramResult {
+        let role = &mut ctx.accounts.role.load_mut()?;
+        role.roles |= roles;
+        Ok(())
+    }
+
+    // removes the ROLE_* bits in `roles`
+    pub fn role_revoke(ctx: Context<RoleConfigure>, roles: u64) -> ProgramResult {
+        let role = &mut ctx.accounts.role.load_mut()?;
+        role.roles &= !roles;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondLimit<'info> {
         pub signer: Signer<'info>,
         pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            constraint = role.load()?.allows(&treasury.key(), &signer.key(), ROLE_RISK_MANAGER) @ ErrorCode::Unauthorized,
+        )]
+        pub role: AccountLoader<'info, Role>,
         #[account(mut, has_one = treasury)]
         pub bond: AccountLoader<'info, Bond>,
     }
 
-    pub fn bond_configure(
-        ctx: Context<BondConfigure>,
-        vesting_period: u64,
-        rfv_rate: u64,
-        min_price: u64,
-        max_payout: u64,
-        max_debt: u64,
-        fee: u64,
-        bcv: u64,
-    ) -> ProgramResult {
-        let bond = &mut ctx.accounts.bond.load_mut()?;
-        require!(vesting_period >= 3600, InvalidParameter);
-        bond.vesting_period = vesting_period;
-        bond.rfv_rate = rfv_rate;
-        bond.min_price = min_price;
+    // lowers a bond's max payout and max debt, limits can only be raised
+    // back with bond_configure (or by gauges)
+    pub fn bond_limit(ctx: Context<BondLimit>, max_payout: u64, max_debt: u64) -> ProgramResult {
+        let bond = &mut ctx.accounts.bond.load_mut_versioned()?;
+        require!(
+            max_payout <= bond.max_payout && max_debt <= bond.max_debt,
+            ErrorCode::InvalidParameter
+        );
         bond.max_payout = max_payout;
         bond.max_debt = max_debt;
-        bond.fee = fee;
-        if bond.bcv > 0 {
-            bond.bcv = bcv;
-        }
         Ok(())
     }
 
     #[derive(Accounts)]
-    pub struct BondDeposit<'info> {
+    pub struct BondPause<'info> {
         pub signer: Signer<'info>,
-        #[account(mut)]
         pub treasury: AccountLoader<'info, Treasury>,
-        #[account(mut, has_one = treasury)]
-        pub bond: AccountLoader<'info, Bond>,
-        #[account(mut, has_one = treasury, has_one = signer)]
-        pub user: AccountLoader<'info, User>,
-        #[account(constraint = mint_bond.key() == bond.load()?.mint_bond)]
-        pub mint_bond: AccountInfo<'info>,
-        #[account(mut, constraint = mint_reserve.key() == treasury.load()?.mint_reserve)]
-        pub mint_reserve: AccountInfo<'info>,
-        #[account(mut, constraint = mint_staking.key() == treasury.load()?.mint_staking)]
-        pub mint_staking: Box<Account<'info, Mint>>,
-        #[account(
-            mut,
-            constraint = token_bond_user.mint == bond.load()?.mint_bond,
-            constraint = token_bond_user.owner == signer.key(),
-        )]
-        pub token_bond_user: Box<Account<'info, TokenAccount>>,
-        #[account(
-            mut,
-            constraint = token_bond_treasury.mint == bond.load()?.mint_bond,
-            constraint = token_bond_treasury.owner == treasury.key(),
-        )]
-        pub token_bond_treasury: Box<Account<'info, TokenAccount>>,
         #[account(
-            mut,
-            constraint = token_reserve_dao.mint == mint_reserve.key(),
-            constraint = token_reserve_dao.owner == treasury.load()?.dao,
+            constraint = role.load()?.allows(&treasury.key(), &signer.key(), ROLE_GUARDIAN) @ ErrorCode::Unauthorized,
         )]
-        pub token_reserve_dao: Box<Account<'info, TokenAccount>>,
+        pub role: AccountLoader<'info, Role>,
+        #[account(mut, has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+    }
+
+    pub fn bond_pause(ctx: Context<BondPause>, paused: bool) -> ProgramResult {
+        let bond = &mut ctx.accounts.bond.load_mut_versioned()?;
+        bond.paused = if paused { unix_now()? } else { 0 };
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct TreasuryTransfer<'info> {
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub treasury: AccountLoader<'info, Treasury>,
         #[account(
-            mut,
-            constraint = token_reserve_staking.mint == mint_reserve.key(),
-            constraint = token_reserve_staking.owner == treasury.key(),
-            constraint = token_reserve_staking.key() == treasury.load()?.token_reserve_staking
+            constraint = role.load()?.allows(&treasury.key(), &signer.key(), ROLE_TREASURER) @ ErrorCode::Unauthorized,
         )]
-        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+        pub role: AccountLoader<'info, Role>,
+        #[account(has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+        // only bonded tokens are reserves, staked and vesting ones aren't
         #[account(
             mut,
-            constraint = token_staking_vesting.mint == mint_staking.key(),
-            constraint = token_staking_vesting.owner == treasury.key(),
-            constraint = token_staking_vesting.key() == treasury.load()?.token_staking_vesting
+            constraint = token_treasury.owner == treasury.key(),
+            constraint = token_treasury.mint == bond.load_versioned()?.mint_bond @ ErrorCode::Unauthorized,
+            constraint = token_treasury.key() != treasury.load_versioned()?.token_reserve_staking @ ErrorCode::Unauthorized,
+            constraint = token_treasury.key() != treasury.load_versioned()?.token_staking_vesting @ ErrorCode::Unauthorized,
         )]
-        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_treasury: Box<Account<'info, TokenAccount>>,
+        #[account(mut, constraint = token_destination.mint == token_treasury.mint)]
+        pub token_destination: Box<Account<'info, TokenAccount>>,
         pub token_program: Program<'info, Token>,
     }
 
-    pub fn bond_deposit(ctx: Context<BondDeposit>, amount: u64, max_price: u64) -> ProgramResult {
-        let now = unix_now()?;
+    // moves reserves (bonded tokens) out of a treasury token account, taking
+    // their rfv out of total_reserves
+    pub fn treasury_transfer(ctx: Context<TreasuryTransfer>, amount: u64) -> ProgramResult {
         let key: Pubkey;
         let bump: u8;
-        let payout: u64;
-        let staked: u64;
-        let fee: u64;
         {
-            let treasury = &mut ctx.accounts.treasury.load_mut()?;
-            let bond = &mut ctx.accounts.bond.load_mut()?;
-            let user = &mut ctx.accounts.user.load_mut()?;
-            let mint_staking = &ctx.accounts.mint_staking;
-            let token_reserve_staking = &ctx.accounts.token_reserve_staking;
+            let value = bond_value(&*ctx.accounts.bond.load_versioned()?, amount)?;
+            let treasury = &mut ctx.accounts.treasury.load_mut_versioned()?;
+            treasury.total_reserves = treasury.total_reserves.saturating_sub(value);
             key = treasury.key;
             bump = treasury.bump;
-            require!(bond.max_payout != 0, ErrorCode::BondNotConfigured);
-
-            // 1. decay total debt
-            let debt_decay = muldiv(
-                bond.total_debt,
-                now - bond.total_debt_last,
-                bond.vesting_period,
-            )?;
-            bond.total_debt = bond.total_debt.saturating_sub(debt_decay);
-            bond.total_debt_last = now;
-            require!(bond.total_debt < bond.max_debt, ErrorCode::BondAtMaxDebt);
-
-            // 2. calculate payout
-            let debt_ratio = muldiv(bond.total_debt, ONE, treasury.total_reserves.max(1))?;
-            let price = (ONE + muldiv(bond.bcv, debt_ratio, ONE)?).max(bond.min_price);
-            let amount_scaled = muldiv(amount, ONE, 10_u64.pow(bond.token_decimals as u32))?;
-            let value = muldiv(amount_scaled, bond.rfv_rate, ONE)?;
-            payout = muldiv(value, ONE, price)?;
-            staked = if mint_staking.supply > 0 {
-                muldiv(payout, mint_staking.supply, token_reserve_staking.amount)?
-            } else {
-                payout
-            };
-            fee = muldiv(payout, bond.fee, ONE)?.min(value.saturating_sub(payout));
-            let mut max_payout = muldiv(treasury.total_reserves, bond.max_payout, ONE)?;
-            if treasury.total_reserves == 0 {
-                max_payout = 1000 * ONE;
-            }
-            msg!(
-                "debtr {} pri {} val {} pay {} fee {} maxpay {}",
-                debt_ratio,
-                price,
-                value,
-                payout,
-                fee,
-                max_payout
-            );
-            require!(payout > ONE / 100, ErrorCode::BondPayoutTooSmall);
-            require!(payout <= max_payout, ErrorCode::BondPayoutTooBig);
-            require!(price <= max_price, ErrorCode::BondPriceSlipped);
-
-            // 3. save results
-            let mut index = 999;
-            for i in 0..user.bonds.len() {
-                if user.bonds[i].bond == Pubkey::default() {
-                    index = i;
-                    break;
-                }
-            }
-            require!(index != 999, ErrorCode::UserNoSpaceForMoreBonds);
-            treasury.total_reserves += value;
-            bond.total_debt += value;
-            bond.total_debt_alltime += value;
-            user.bonds[index].bond = ctx.accounts.bond.key();
-            user.bonds[index].price = price;
-            user.bonds[index].payout = payout;
-            user.bonds[index].staked = staked;
-            user.bonds[index].claimed = 0;
-            user.bonds[index].vesting_start = now;
-            user.bonds[index].vesting_period = bond.vesting_period;
-
-            emit!(EventBondDeposit {
-                signer: ctx.accounts.signer.key(),
-                treasury: ctx.accounts.treasury.key(),
-                bond: ctx.accounts.bond.key(),
-                user: ctx.accounts.user.key(),
-                amount,
-                price,
-                payout,
-                staked,
-            });
         }
 
-        // 4. transfers
         token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.token_bond_user.to_account_info(),
-                    to: ctx.accounts.token_bond_treasury.to_account_info(),
-                    authority: ctx.accounts.signer.to_account_info(),
-                },
-            ),
-            amount,
-        )?;
-
-        if fee > 0 {
-            token::mint_to(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    token::MintTo {
-                        mint: ctx.accounts.mint_reserve.to_account_info(),
-                        to: ctx.accounts.token_reserve_dao.to_account_info(),
-                        authority: ctx.accounts.treasury.to_account_info(),
-                    },
-                    &[&[b"treasury", key.as_ref(), &[bump]]],
-                ),
-                fee,
-            )?;
-        }
-
-        token::mint_to(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                token::MintTo {
-                    mint: ctx.accounts.mint_reserve.to_account_info(),
-                    to: ctx.accounts.token_reserve_staking.to_account_info(),
-                    authority: ctx.accounts.treasury.to_account_info(),
-                },
-                &[&[b"treasury", key.as_ref(), &[bump]]],
-            ),
-            payout,
-        )?;
-        token::mint_to(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                token::MintTo {
-                    mint: ctx.accounts.mint_staking.to_account_info(),
-                    to: ctx.accounts.token_staking_vesting.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_treasury.to_account_info(),
+                    to: ctx.accounts.token_destination.to_account_info(),
                     authority: ctx.accounts.treasury.to_account_info(),
                 },
                 &[&[b"treasury", key.as_ref(), &[bump]]],
             ),
-            staked,
+            amount,
         )?;
 
         Ok(())
     }
 
     #[derive(Accounts)]
-    pub struct BondWithdraw<'info> {
+    #[instruction(bump: u8)]
+    pub struct UserInitialize<'info> {
+        #[account(mut)]
         pub signer: Signer<'info>,
         pub treasury: AccountLoader<'info, Treasury>,
-        #[account(mut, has_one = treasury, has_one = signer)]
-        pub user: AccountLoader<'info, User>,
-        #[account(constraint = mint_staking.key() == treasury.load()?.mint_staking)]
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"user", treasury.key().as_ref(), signer.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<User>(),
+        )]
+        pub user: AccountLoader<'info, User>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn user_initialize(ctx: Context<UserInitialize>, bump: u8) -> ProgramResult {
+        let user = &mut ctx.accounts.user.load_init()?;
+        user.treasury = ctx.accounts.treasury.key();
+        user.signer = ctx.accounts.signer.key();
+        user.bump = bump;
+        user.version = USER_VERSION;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct UserClose<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        #[account(mut, has_one = signer, close = signer)]
+        pub user: AccountLoader<'info, User>,
+    }
+
+    // refunds the user's rent once nothing is left in it, it can be
+    // initialized again later
+    pub fn user_close(ctx: Context<UserClose>) -> ProgramResult {
+        let user = ctx.accounts.user.load_versioned()?;
+        for i in 0..user.bonds.len() {
+            require!(
+                user.bonds[i].bond == Pubkey::default(),
+                ErrorCode::UserNotEmpty
+            );
+        }
+        require!(
+            user.warmup_amount == 0 && user.cooldown_amount == 0,
+            ErrorCode::UserNotEmpty
+        );
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct MigrateUser<'info> {
+        pub signer: Signer<'info>,
+        #[account(mut, has_one = signer)]
+        pub user: AccountLoader<'info, User>,
+    }
+
+    // users kept their size, the version took reserved space so they're
+    // upgraded in place
+    pub fn migrate_user(ctx: Context<MigrateUser>) -> ProgramResult {
+        let user = &mut ctx.accounts.user.load_mut()?;
+        migration_check(user.version, USER_VERSION)?;
+        user.version = USER_VERSION;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct MigrateTreasuryBegin<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub treasury: AccountInfo<'info>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"migration", treasury.key().as_ref()],
+            bump = bump,
+            space = 8 + 1 + 32 + 4 + treasury.data_len(),
+        )]
+        pub migration: Account<'info, Migration>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // moves a treasury's data to a Migration and closes it, the mints and
+    // token accounts it's the authority of are untouched as its address
+    // doesn't change, migrate_treasury_finish must follow
+    pub fn migrate_treasury_begin(ctx: Context<MigrateTreasuryBegin>, bump: u8) -> ProgramResult {
+        let info = ctx.accounts.treasury.to_account_info();
+        let treasury: Treasury = migration_read(&info, ctx.program_id)?;
+        require!(
+            treasury.authority == ctx.accounts.signer.key(),
+            ErrorCode::Unauthorized
+        );
+        migration_begin(
+            &mut ctx.accounts.migration,
+            bump,
+            &info,
+            &ctx.accounts.signer.to_account_info(),
+        )
+    }
+
+    #[derive(Accounts)]
+    #[instruction(key: Pubkey, bump: u8)]
+    pub struct MigrateTreasuryFinish<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"treasury", key.as_ref()],
+            bump = bump,
+            space = 8 + size_of::<Treasury>(),
+        )]
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            mut,
+            seeds = [b"migration", treasury.key().as_ref()],
+            bump = migration.bump,
+            close = signer,
+        )]
+        pub migration: Account<'info, Migration>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn migrate_treasury_finish(
+        ctx: Context<MigrateTreasuryFinish>,
+        _key: Pubkey,
+        _bump: u8,
+    ) -> ProgramResult {
+        let upgraded: Treasury = migration_upgrade(&ctx.accounts.migration.data)?;
+        require!(
+            upgraded.authority == ctx.accounts.signer.key(),
+            ErrorCode::Unauthorized
+        );
+        let treasury = &mut ctx.accounts.treasury.load_init()?;
+        **treasury = upgraded;
+        treasury.version = TREASURY_VERSION;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct MigrateBondBegin<'info> {
+        #[account(
+            mut,
+            constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized,
+        )]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut)]
+        pub bond: AccountInfo<'info>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"migration", bond.key().as_ref()],
+            bump = bump,
+            space = 8 + 1 + 32 + 4 + bond.data_len(),
+        )]
+        pub migration: Account<'info, Migration>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // the treasury is migrated first, users' bonds keep pointing to the
+    // bond's unchanged address
+    pub fn migrate_bond_begin(ctx: Context<MigrateBondBegin>, bump: u8) -> ProgramResult {
+        let info = ctx.accounts.bond.to_account_info();
+        let bond: Bond = migration_read(&info, ctx.program_id)?;
+        require!(
+            bond.treasury == ctx.accounts.treasury.key(),
+            ErrorCode::InvalidParameter
+        );
+        migration_begin(
+            &mut ctx.accounts.migration,
+            bump,
+            &info,
+            &ctx.accounts.signer.to_account_info(),
+        )
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct MigrateBondFinish<'info> {
+        #[account(
+            mut,
+            constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized,
+        )]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"bond", treasury.key().as_ref(), mint_bond.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<Bond>(),
+        )]
+        pub bond: AccountLoader<'info, Bond>,
+        pub mint_bond: AccountInfo<'info>,
+        #[account(
+            mut,
+            seeds = [b"migration", bond.key().as_ref()],
+            bump = migration.bump,
+            close = signer,
+        )]
+        pub migration: Account<'info, Migration>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn migrate_bond_finish(ctx: Context<MigrateBondFinish>, _bump: u8) -> ProgramResult {
+        let upgraded: Bond = migration_upgrade(&ctx.accounts.migration.data)?;
+        require!(
+            upgraded.treasury == ctx.accounts.treasury.key()
+                && upgraded.mint_bond == ctx.accounts.mint_bond.key(),
+            ErrorCode::InvalidParameter
+        );
+        let bond = &mut ctx.accounts.bond.load_init()?;
+        **bond = upgraded;
+        bond.version = BOND_VERSION;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct BondInitialize<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            constraint = role.load()?.allows(&treasury.key(), &signer.key(), ROLE_BOND_MANAGER) @ ErrorCode::Unauthorized,
+        )]
+        pub role: AccountLoader<'info, Role>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"bond", treasury.key().as_ref(), mint_bond.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<Bond>(),
+        )]
+        pub bond: AccountLoader<'info, Bond>,
+        pub mint_bond: Account<'info, Mint>,
+        // a bond being migrated is closed until migrate_bond_finish, creating
+        // it again would lose its data
+        #[account(
+            seeds = [b"migration", bond.key().as_ref()],
+            bump,
+            constraint = migration.lamports() == 0 @ ErrorCode::AccountVersion,
+        )]
+        pub migration: AccountInfo<'info>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn bond_initialize(ctx: Context<BondInitialize>, _bump: u8) -> ProgramResult {
+        let bond = &mut ctx.accounts.bond.load_init()?;
+        bond.treasury = ctx.accounts.treasury.key();
+        bond.mint_bond = ctx.accounts.mint_bond.key();
+        bond.token_decimals = ctx.accounts.mint_bond.decimals;
+        bond.total_debt_last = unix_now()?;
+        bond.version = BOND_VERSION;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondConfigure<'info> {
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            constraint = role.load()?.allows(&treasury.key(), &signer.key(), ROLE_BOND_MANAGER) @ ErrorCode::Unauthorized,
+        )]
+        pub role: AccountLoader<'info, Role>,
+        #[account(mut, has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn bond_configure(
+        ctx: Context<BondConfigure>,
+        vesting_period: u64,
+        rfv_rate: u64,
+        min_price: u64,
+        max_payout: u64,
+        max_debt: u64,
+        fee: u64,
+        bcv: u64,
+    ) -> ProgramResult {
+        bond_apply(
+            &mut *ctx.accounts.bond.load_mut_versioned()?,
+            &BondParams {
+                vesting_period,
+                rfv_rate,
+                min_price,
+                max_payout,
+                max_debt,
+                fee,
+                bcv,
+            },
+        )
+    }
+
+    // limits on how fast a single user, or everyone in the same slot, can
+    // use up a bond's max_debt. 0 disables either
+    pub fn bond_configure_caps(
+        ctx: Context<BondConfigure>,
+        max_debt_user: u64,
+        max_payout_slot: u64,
+    ) -> ProgramResult {
+        let bond = &mut ctx.accounts.bond.load_mut_versioned()?;
+        bond.max_debt_user = max_debt_user;
+        bond.max_payout_slot = max_payout_slot;
+        Ok(())
+    }
+
+    // exit_penalty of 0 disables bond_exit_early
+    pub fn bond_configure_exit(ctx: Context<BondConfigure>, exit_penalty: u64) -> ProgramResult {
+        require!(exit_penalty <= ONE, ErrorCode::InvalidParameter);
+        let bond = &mut ctx.accounts.bond.load_mut_versioned()?;
+        bond.exit_penalty = exit_penalty;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct BondAllowlistInitialize<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            constraint = role.load()?.allows(&treasury.key(), &signer.key(), ROLE_BOND_MANAGER) @ ErrorCode::Unauthorized,
+        )]
+        pub role: AccountLoader<'info, Role>,
+        #[account(has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"bond_allowlist", bond.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<BondAllowlist>(),
+        )]
+        pub allowlist: AccountLoader<'info, BondAllowlist>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn bond_allowlist_initialize(
+        ctx: Context<BondAllowlistInitialize>,
+        bump: u8,
+        root: [u8; 32],
+    ) -> ProgramResult {
+        let allowlist = &mut ctx.accounts.allowlist.load_init()?;
+        allowlist.bump = bump;
+        allowlist.bond = ctx.accounts.bond.key();
+        allowlist.root = root;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondAllowlistConfigure<'info> {
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            constraint = role.load()?.allows(&treasury.key(), &signer.key(), ROLE_BOND_MANAGER) @ ErrorCode::Unauthorized,
+        )]
+        pub role: AccountLoader<'info, Role>,
+        #[account(has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+        #[account(mut, has_one = bond)]
+        pub allowlist: AccountLoader<'info, BondAllowlist>,
+    }
+
+    // allowances already used are kept, so a new root should keep the same
+    // max_amount for wallets that deposited
+    pub fn bond_allowlist_configure(
+        ctx: Context<BondAllowlistConfigure>,
+        root: [u8; 32],
+    ) -> ProgramResult {
+        ctx.accounts.allowlist.load_mut()?.root = root;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct BondScheduleInitialize<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            constraint = role.load()?.allows(&treasury.key(), &signer.key(), ROLE_BOND_MANAGER) @ ErrorCode::Unauthorized,
+        )]
+        pub role: AccountLoader<'info, Role>,
+        #[account(has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"bond_schedule", bond.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<BondSchedule>(),
+        )]
+        pub schedule: AccountLoader<'info, BondSchedule>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn bond_schedule_initialize(
+        ctx: Context<BondScheduleInitialize>,
+        bump: u8,
+        kind: u64,
+        cliff: u64,
+    ) -> ProgramResult {
+        let schedule = &mut ctx.accounts.schedule.load_init()?;
+        schedule.bump = bump;
+        schedule.bond = ctx.accounts.bond.key();
+        schedule_apply(schedule, &*ctx.accounts.bond.load_versioned()?, kind, cliff)
+    }
+
+    #[derive(Accounts)]
+    pub struct BondScheduleConfigure<'info> {
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            constraint = role.load()?.allows(&treasury.key(), &signer.key(), ROLE_BOND_MANAGER) @ ErrorCode::Unauthorized,
+        )]
+        pub role: AccountLoader<'info, Role>,
+        #[account(has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+        #[account(mut, has_one = bond)]
+        pub schedule: AccountLoader<'info, BondSchedule>,
+    }
+
+    // applies to new deposits only, existing ones keep their snapshot
+    pub fn bond_schedule_configure(
+        ctx: Context<BondScheduleConfigure>,
+        kind: u64,
+        cliff: u64,
+    ) -> ProgramResult {
+        schedule_apply(
+            &mut *ctx.accounts.schedule.load_mut()?,
+            &*ctx.accounts.bond.load_versioned()?,
+            kind,
+            cliff,
+        )
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct BondAllowanceInitialize<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub bond: AccountLoader<'info, Bond>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"bond_allowance", bond.key().as_ref(), signer.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<BondAllowance>(),
+        )]
+        pub allowance: AccountLoader<'info, BondAllowance>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn bond_allowance_initialize(
+        ctx: Context<BondAllowanceInitialize>,
+        bump: u8,
+    ) -> ProgramResult {
+        let allowance = &mut ctx.accounts.allowance.load_init()?;
+        allowance.bump = bump;
+        allowance.bond = ctx.accounts.bond.key();
+        allowance.signer = ctx.accounts.signer.key();
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondDeposit<'info> {
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+        // both might not exist, bonds with an allowlist root need deposits to
+        // come with a proof and an initialized allowance
+        #[account(seeds = [b"bond_allowlist", bond.key().as_ref()], bump)]
+        pub allowlist: AccountInfo<'info>,
+        #[account(
+            mut,
+            seeds = [b"bond_allowance", bond.key().as_ref(), signer.key().as_ref()],
+            bump,
+        )]
+        pub allowance: AccountInfo<'info>,
+        // might not exist either, deposits vest linearly then
+        #[account(seeds = [b"bond_schedule", bond.key().as_ref()], bump)]
+        pub schedule: AccountInfo<'info>,
+        // recorded into when it exists
+        #[account(mut, seeds = [b"bond_observations", bond.key().as_ref()], bump)]
+        pub observations: AccountInfo<'info>,
+        // might not exist, deposits don't look at the market price then
+        #[account(seeds = [b"oracle", treasury.key().as_ref()], bump)]
+        pub oracle: AccountInfo<'info>,
+        #[account(mut, has_one = treasury, has_one = signer)]
+        pub user: AccountLoader<'info, User>,
+        #[account(constraint = mint_bond.key() == bond.load_versioned()?.mint_bond)]
+        pub mint_bond: AccountInfo<'info>,
+        #[account(mut, constraint = mint_reserve.key() == treasury.load_versioned()?.mint_reserve)]
+        pub mint_reserve: AccountInfo<'info>,
+        #[account(mut, constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_bond_user.mint == bond.load_versioned()?.mint_bond,
+            constraint = token_bond_user.owner == signer.key(),
+        )]
+        pub token_bond_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_bond_treasury.mint == bond.load_versioned()?.mint_bond,
+            constraint = token_bond_treasury.owner == treasury.key(),
+        )]
+        pub token_bond_treasury: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_reserve_dao.mint == mint_reserve.key(),
+            constraint = token_reserve_dao.owner == treasury.load_versioned()?.dao,
+        )]
+        pub token_reserve_dao: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_reserve_staking.mint == mint_reserve.key(),
+            constraint = token_reserve_staking.owner == treasury.key(),
+            constraint = token_reserve_staking.key() == treasury.load_versioned()?.token_reserve_staking
+        )]
+        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // a referrer can be passed as remaining accounts (its Referrer and
+    // reserve token account) to get its share of the fee
+    pub fn bond_deposit<'info>(
+        ctx: Context<'_, '_, '_, 'info, BondDeposit<'info>>,
+        amount: u64,
+        max_price: u64,
+        max_amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> ProgramResult {
+        let now = unix_now()?;
+        let key: Pubkey;
+        let bump: u8;
+        let payout: u64;
+        let staked: u64;
+        let fee: u64;
+        let mut referrer = Pubkey::default();
+        let mut referrer_fee = 0;
+        if *ctx.accounts.allowlist.owner == *ctx.program_id {
+            let allowlist = AccountLoader::<BondAllowlist>::try_from(&ctx.accounts.allowlist)?;
+            let root = allowlist.load()?.root;
+            if root != [0; 32] {
+                let leaf = hashv(&[
+                    ctx.accounts.signer.key().as_ref(),
+                    &max_amount.to_le_bytes(),
+                ]);
+                require!(
+                    merkle_verify(&proof, root, leaf.to_bytes()),
+                    ErrorCode::BondNotAllowlisted
+                );
+                require!(
+                    *ctx.accounts.allowance.owner == *ctx.program_id,
+                    ErrorCode::BondNotAllowlisted
+                );
+                let allowance = AccountLoader::<BondAllowance>::try_from(&ctx.accounts.allowance)?;
+                let allowance = &mut allowance.load_mut()?;
+                allowance.used = allowance
+                    .used
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::Overflow)?;
+                require!(
+                    allowance.used <= max_amount,
+                    ErrorCode::BondAllowanceExceeded
+                );
+            }
+        }
+        {
+            let treasury = &mut ctx.accounts.treasury.load_mut_versioned()?;
+            let bond = &mut ctx.accounts.bond.load_mut_versioned()?;
+            let user = &mut ctx.accounts.user.load_mut_versioned()?;
+            let mint_staking = &ctx.accounts.mint_staking;
+            let token_reserve_staking = &ctx.accounts.token_reserve_staking;
+            key = treasury.key;
+            bump = treasury.bump;
+            require!(bond.max_payout != 0, ErrorCode::BondNotConfigured);
+            require!(bond.paused == 0, ErrorCode::BondPaused);
+
+            // 1. decay total debt & 2. calculate payout
+            let quote = bond_quote(bond, treasury.total_reserves, amount, now)?;
+            bond.total_debt = quote.total_debt;
+            bond.total_debt_last = now;
+            require!(bond.total_debt < bond.max_debt, ErrorCode::BondAtMaxDebt);
+            let price = quote.price;
+            let value = quote.value;
+            payout = quote.payout;
+            staked = staking_shares(
+                payout,
+                mint_staking.supply,
+                treasury.staking_pool(token_reserve_staking.amount),
+            )?;
+            fee = quote.fee;
+            #[cfg(feature = "debug-logs")]
+            msg!(
+                "debtr {} pri {} val {} pay {} fee {} maxpay {}",
+                quote.debt_ratio,
+                price,
+                value,
+                payout,
+                fee,
+                quote.max_payout
+            );
+            require!(payout > ONE / 100, ErrorCode::BondPayoutTooSmall);
+            require!(payout <= quote.max_payout, ErrorCode::BondPayoutTooBig);
+            require!(price <= max_price, ErrorCode::BondPriceSlipped);
+            if *ctx.accounts.oracle.owner == *ctx.program_id {
+                let loader = AccountLoader::<Oracle>::try_from(&ctx.accounts.oracle)?;
+                let oracle = loader.load()?;
+                require!(
+                    now.saturating_sub(oracle.updated) <= oracle.max_age,
+                    ErrorCode::OracleStale
+                );
+                require!(price <= oracle.price, ErrorCode::BondAboveMarket);
+                let supply = Account::<Mint>::try_from(&ctx.accounts.mint_reserve)?.supply;
+                require!(
+                    price >= muldiv(treasury.total_reserves, ONE, supply.max(1))?,
+                    ErrorCode::BondBelowBacking
+                );
+            }
+            user.debt = debt_decay(user.debt, user.debt_last, now, bond.vesting_period)?;
+            user.debt_last = now;
+            require!(
+                bond.max_debt_user == 0 || user.debt + value <= bond.max_debt_user,
+                ErrorCode::BondUserAtMaxDebt
+            );
+            let slot = Clock::get()?.slot;
+            if bond.slot != slot {
+                bond.slot = slot;
+                bond.slot_payout = 0;
+            }
+            bond.slot_payout += payout;
+            require!(
+                bond.max_payout_slot == 0 || bond.slot_payout <= bond.max_payout_slot,
+                ErrorCode::BondSlotAtMaxPayout
+            );
+
+            // 3. save results
+            let mut index = 999;
+            for i in 0..user.bonds.len() {
+                if user.bonds[i].bond == Pubkey::default() {
+                    index = i;
+                    break;
+                }
+            }
+            require!(index != 999, ErrorCode::UserNoSpaceForMoreBonds);
+            treasury.total_reserves += value;
+            bond.total_debt += value;
+            bond.total_debt_alltime += value;
+            user.debt += value;
+            user.bonds[index].bond = ctx.accounts.bond.key();
+            user.bonds[index].price = price;
+            user.bonds[index].payout = payout;
+            user.bonds[index].staked = staked;
+            user.bonds[index].claimed = 0;
+            user.bonds[index].vesting_start = now;
+            user.bonds[index].vesting_period = bond.vesting_period;
+            if *ctx.accounts.schedule.owner == *ctx.program_id {
+                let loader = AccountLoader::<BondSchedule>::try_from(&ctx.accounts.schedule)?;
+                let schedule = loader.load()?;
+                user.bonds[index].vesting_kind = schedule.kind;
+                user.bonds[index].vesting_cliff = schedule.cliff;
+            } else {
+                user.bonds[index].vesting_kind = VESTING_LINEAR;
+                user.bonds[index].vesting_cliff = 0;
+            }
+            if *ctx.accounts.observations.owner == *ctx.program_id {
+                let loader =
+                    AccountLoader::<BondObservations>::try_from(&ctx.accounts.observations)?;
+                observation_record(&mut *loader.load_mut()?, now, price, quote.debt_ratio);
+            }
+
+            if !ctx.remaining_accounts.is_empty() {
+                require!(
+                    ctx.remaining_accounts.len() >= 2,
+                    ErrorCode::InvalidParameter
+                );
+                let loader = AccountLoader::<Referrer>::try_from(&ctx.remaining_accounts[0])?;
+                let r = &mut loader.load_mut()?;
+                require!(
+                    r.treasury == ctx.accounts.treasury.key()
+                        && r.token_reserve == ctx.remaining_accounts[1].key(),
+                    ErrorCode::InvalidParameter
+                );
+                referrer = loader.key();
+                referrer_fee = muldiv(fee, r.fee.min(r.fee_max), ONE)?;
+                r.volume += value;
+                r.earned += referrer_fee;
+                r.deposits += 1;
+            }
+
+            emit!(EventBondDeposit {
+                signer: ctx.accounts.signer.key(),
+                treasury: ctx.accounts.treasury.key(),
+                bond: ctx.accounts.bond.key(),
+                user: ctx.accounts.user.key(),
+                amount,
+                price,
+                payout,
+                staked,
+                referrer,
+                referrer_fee,
+            });
+        }
+
+        // 4. transfers
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_bond_user.to_account_info(),
+                    to: ctx.accounts.token_bond_treasury.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        if fee > referrer_fee {
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::MintTo {
+                        mint: ctx.accounts.mint_reserve.to_account_info(),
+                        to: ctx.accounts.token_reserve_dao.to_account_info(),
+                        authority: ctx.accounts.treasury.to_account_info(),
+                    },
+                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                ),
+                fee - referrer_fee,
+            )?;
+        }
+        if referrer_fee > 0 {
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::MintTo {
+                        mint: ctx.accounts.mint_reserve.to_account_info(),
+                        to: ctx.remaining_accounts[1].clone(),
+                        authority: ctx.accounts.treasury.to_account_info(),
+                    },
+                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                ),
+                referrer_fee,
+            )?;
+        }
+
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.mint_reserve.to_account_info(),
+                    to: ctx.accounts.token_reserve_staking.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                &[&[b"treasury", key.as_ref(), &[bump]]],
+            ),
+            payout,
+        )?;
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.mint_staking.to_account_info(),
+                    to: ctx.accounts.token_staking_vesting.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                &[&[b"treasury", key.as_ref(), &[bump]]],
+            ),
+            staked,
+        )?;
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct BondObservationsInitialize<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub bond: AccountLoader<'info, Bond>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"bond_observations", bond.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<BondObservations>(),
+        )]
+        pub observations: AccountLoader<'info, BondObservations>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // anyone can pay for a bond's price history
+    pub fn bond_observations_initialize(
+        ctx: Context<BondObservationsInitialize>,
+        bump: u8,
+    ) -> ProgramResult {
+        ctx.accounts.bond.load_versioned()?;
+        let observations = &mut ctx.accounts.observations.load_init()?;
+        observations.bump = bump;
+        observations.bond = ctx.accounts.bond.key();
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondObserve<'info> {
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+        #[account(mut, has_one = bond)]
+        pub observations: AccountLoader<'info, BondObservations>,
+    }
+
+    // crank recording a bond's current price, so the history keeps going
+    // between deposits
+    pub fn bond_observe(ctx: Context<BondObserve>) -> ProgramResult {
+        let now = unix_now()?;
+        let treasury = ctx.accounts.treasury.load_versioned()?;
+        let quote = bond_quote(
+            &*ctx.accounts.bond.load_versioned()?,
+            treasury.total_reserves,
+            0,
+            now,
+        )?;
+        observation_record(
+            &mut *ctx.accounts.observations.load_mut()?,
+            now,
+            quote.price,
+            quote.debt_ratio,
+        );
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondTwap<'info> {
+        #[account(has_one = bond)]
+        pub observations: AccountLoader<'info, BondObservations>,
+        pub bond: AccountLoader<'info, Bond>,
+    }
+
+    // sets the return data to the bond's time weighted average price over
+    // the last `window` seconds (u64 le, 1e9)
+    pub fn bond_twap(ctx: Context<BondTwap>, window: u64) -> ProgramResult {
+        let twap = observation_twap(&*ctx.accounts.observations.load()?, unix_now()?, window)?;
+        set_return_data(&twap.to_le_bytes());
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct ReferrerInitialize<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"referrer", treasury.key().as_ref(), signer.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<Referrer>(),
+        )]
+        pub referrer: AccountLoader<'info, Referrer>,
+        #[account(constraint = token_reserve.mint == treasury.load_versioned()?.mint_reserve)]
+        pub token_reserve: Box<Account<'info, TokenAccount>>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // referrers earn nothing until the authority sets their fee_max
+    pub fn referrer_initialize(ctx: Context<ReferrerInitialize>, bump: u8) -> ProgramResult {
+        let referrer = &mut ctx.accounts.referrer.load_init()?;
+        referrer.bump = bump;
+        referrer.treasury = ctx.accounts.treasury.key();
+        referrer.signer = ctx.accounts.signer.key();
+        referrer.token_reserve = ctx.accounts.token_reserve.key();
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct ReferrerConfigure<'info> {
+        pub signer: Signer<'info>,
+        #[account(mut, has_one = signer)]
+        pub referrer: AccountLoader<'info, Referrer>,
+    }
+
+    pub fn referrer_configure(ctx: Context<ReferrerConfigure>, fee: u64) -> ProgramResult {
+        let referrer = &mut ctx.accounts.referrer.load_mut()?;
+        require!(fee <= referrer.fee_max, ErrorCode::InvalidParameter);
+        referrer.fee = fee;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct ReferrerApprove<'info> {
+        #[account(constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub referrer: AccountLoader<'info, Referrer>,
+    }
+
+    // caps the fee a referrer can set, lowering it if needed
+    pub fn referrer_approve(ctx: Context<ReferrerApprove>, fee_max: u64) -> ProgramResult {
+        require!(fee_max <= ONE, ErrorCode::InvalidParameter);
+        let referrer = &mut ctx.accounts.referrer.load_mut()?;
+        referrer.fee_max = fee_max;
+        referrer.fee = referrer.fee.min(fee_max);
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondWithdraw<'info> {
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        // the signer's User, or a BondPosition followed by its mint and the
+        // signer's token_position in the remaining accounts
+        #[account(mut)]
+        pub user: AccountInfo<'info>,
+        #[account(constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_user.mint == mint_staking.key(),
+            constraint = token_staking_user.owner == signer.key(),
+        )]
+        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // claims what vested of a user's bond, or of a position for the holder
+    // of its token (index is ignored then). a fully claimed position has
+    // its token burned and is closed
+    pub fn bond_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, BondWithdraw<'info>>,
+        index: u64,
+    ) -> ProgramResult {
+        let now = unix_now()?;
+        let key: Pubkey;
+        let bump: u8;
+        let amount: u64;
+        let done: bool;
+        let tokenized = !ctx.remaining_accounts.is_empty();
+        {
+            let treasury = &mut ctx.accounts.treasury.load_versioned()?;
+            key = treasury.key;
+            bump = treasury.bump;
+
+            let bond_key: Pubkey;
+            if tokenized {
+                require!(
+                    ctx.remaining_accounts.len() >= 2,
+                    ErrorCode::InvalidParameter
+                );
+                let loader = AccountLoader::<BondPosition>::try_from(&ctx.accounts.user)?;
+                let position = &mut loader.load_mut()?;
+                let token_position = Account::<TokenAccount>::try_from(&ctx.remaining_accounts[1])?;
+                require!(
+                    position.treasury == ctx.accounts.treasury.key()
+                        && position.mint == ctx.remaining_accounts[0].key(),
+                    ErrorCode::InvalidParameter
+                );
+                require!(
+                    token_position.mint == position.mint
+                        && token_position.owner == ctx.accounts.signer.key()
+                        && token_position.amount == 1,
+                    ErrorCode::Unauthorized
+                );
+                let (a, d) = position.bond.claim(now)?;
+                amount = a;
+                done = d;
+                bond_key = position.bond.bond;
+            } else {
+                let loader = AccountLoader::<User>::try_from(&ctx.accounts.user)?;
+                let user = &mut loader.load_mut_versioned()?;
+                require!(
+                    user.treasury == ctx.accounts.treasury.key()
+                        && user.signer == ctx.accounts.signer.key(),
+                    ErrorCode::Unauthorized
+                );
+                require!(index < user.bonds.len() as u64, ErrorCode::Overflow);
+                require!(
+                    user.bonds[index as usize].bond != Pubkey::default(),
+                    ErrorCode::Unknown
+                );
+                let (a, d) = user.bonds[index as usize].claim(now)?;
+                amount = a;
+                done = d;
+                bond_key = user.bonds[index as usize].bond;
+                if done {
+                    user.bonds[index as usize] = UserBond::default();
+                }
+            }
+
+            emit!(EventBondWithdraw {
+                signer: ctx.accounts.signer.key(),
+                treasury: ctx.accounts.treasury.key(),
+                bond: bond_key,
+                user: ctx.accounts.user.key(),
+                done,
+                amount,
+            });
+        }
+
+        if amount > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.token_staking_vesting.to_account_info(),
+                        to: ctx.accounts.token_staking_user.to_account_info(),
+                        authority: ctx.accounts.treasury.to_account_info(),
+                    },
+                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                ),
+                amount,
+            )?;
+        }
+
+        if tokenized && done {
+            token::burn(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Burn {
+                        mint: ctx.remaining_accounts[0].clone(),
+                        to: ctx.remaining_accounts[1].clone(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                ),
+                1,
+            )?;
+            AccountLoader::<BondPosition>::try_from(&ctx.accounts.user)?
+                .close(ctx.accounts.signer.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondExitEarly<'info> {
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+        #[account(mut, has_one = treasury, has_one = signer)]
+        pub user: AccountLoader<'info, User>,
+        #[account(mut, constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_user.mint == mint_staking.key(),
+            constraint = token_staking_user.owner == signer.key(),
+        )]
+        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // releases everything left in a bond, minus exit_penalty of the part
+    // not vested yet. the penalty is burned, raising the value of every
+    // other staking token
+    pub fn bond_exit_early(ctx: Context<BondExitEarly>, index: u64) -> ProgramResult {
+        let now = unix_now()?;
+        let key: Pubkey;
+        let bump: u8;
+        let amount: u64;
+        let penalty: u64;
+        {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            let bond = ctx.accounts.bond.load_versioned()?;
+            let user = &mut ctx.accounts.user.load_mut_versioned()?;
+            key = treasury.key;
+            bump = treasury.bump;
+            require!(bond.exit_penalty > 0, ErrorCode::ExitDisabled);
+
+            require!(index < user.bonds.len() as u64, ErrorCode::Overflow);
+            let user_bond = user.bonds[index as usize];
+            require!(
+                user_bond.bond == ctx.accounts.bond.key(),
+                ErrorCode::Unknown
+            );
+            require!(!user_bond.tokenized(), ErrorCode::BondTokenized);
+            let (a, p) = exit_early(&user_bond, bond.exit_penalty, now)?;
+            amount = a;
+            penalty = p;
+            user.bonds[index as usize] = UserBond::default();
+
+            emit!(EventBondExit {
+                signer: ctx.accounts.signer.key(),
+                treasury: ctx.accounts.treasury.key(),
+                bond: user_bond.bond,
+                user: ctx.accounts.user.key(),
+                amount,
+                penalty,
+            });
+        }
+
+        if amount > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.token_staking_vesting.to_account_info(),
+                        to: ctx.accounts.token_staking_user.to_account_info(),
+                        authority: ctx.accounts.treasury.to_account_info(),
+                    },
+                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                ),
+                amount,
+            )?;
+        }
+        if penalty > 0 {
+            token::burn(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Burn {
+                        mint: ctx.accounts.mint_staking.to_account_info(),
+                        to: ctx.accounts.token_staking_vesting.to_account_info(),
+                        authority: ctx.accounts.treasury.to_account_info(),
+                    },
+                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                ),
+                penalty,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8, mint_bump: u8, token_bump: u8, key: Pubkey)]
+    pub struct BondTokenize<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury, has_one = signer)]
+        pub user: AccountLoader<'info, User>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"bond_position_mint", treasury.key().as_ref(), key.as_ref()],
+            bump = mint_bump,
+            owner = token::ID,
+            space = Mint::LEN
+        )]
+        pub mint: AccountInfo<'info>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"bond_position", mint.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<BondPosition>(),
+        )]
+        pub position: AccountLoader<'info, BondPosition>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"bond_position_token", mint.key().as_ref()],
+            bump = token_bump,
+            owner = token::ID,
+            space = TokenAccount::LEN
+        )]
+        pub token_position: AccountInfo<'info>,
+        pub rent: Sysvar<'info, Rent>,
+        pub token_program: Program<'info, Token>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // moves a user's bond into a BondPosition represented by a new mint,
+    // minting its only token to token_position (owned by the signer) before
+    // dropping the mint authority. the bond's slot stays taken, emptied, so
+    // it still counts towards the user's slots until vested
+    pub fn bond_tokenize(
+        ctx: Context<BondTokenize>,
+        bump: u8,
+        _mint_bump: u8,
+        _token_bump: u8,
+        _key: Pubkey,
+        index: u64,
+    ) -> ProgramResult {
+        let key: Pubkey;
+        let treasury_bump: u8;
+        {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            key = treasury.key;
+            treasury_bump = treasury.bump;
+        }
+        token::initialize_mint(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::InitializeMint {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+            ),
+            0,
+            ctx.accounts.treasury.to_account_info().key,
+            None,
+        )?;
+        token::initialize_account(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::InitializeAccount {
+                account: ctx.accounts.token_position.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.signer.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+        ))?;
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.token_position.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                &[&[b"treasury", key.as_ref(), &[treasury_bump]]],
+            ),
+            1,
+        )?;
+        token::set_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::SetAuthority {
+                    current_authority: ctx.accounts.treasury.to_account_info(),
+                    account_or_mint: ctx.accounts.mint.to_account_info(),
+                },
+                &[&[b"treasury", key.as_ref(), &[treasury_bump]]],
+            ),
+            AuthorityType::MintTokens,
+            None,
+        )?;
+
+        let user = &mut ctx.accounts.user.load_mut_versioned()?;
+        require!(index < user.bonds.len() as u64, ErrorCode::Overflow);
+        let user_bond = &mut user.bonds[index as usize];
+        require!(user_bond.bond != Pubkey::default(), ErrorCode::Unknown);
+        require!(!user_bond.tokenized(), ErrorCode::BondTokenized);
+        let position = &mut ctx.accounts.position.load_init()?;
+        position.bump = bump;
+        position.treasury = ctx.accounts.treasury.key();
+        position.mint = ctx.accounts.mint.key();
+        position.bond = *user_bond;
+        user_bond.staked = 0;
+        user_bond.claimed = 0;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondPositionExitEarly<'info> {
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+        #[account(
+            mut,
+            has_one = treasury,
+            has_one = mint,
+            constraint = position.load()?.bond.bond == bond.key() @ ErrorCode::Unknown,
+            close = signer,
+        )]
+        pub position: AccountLoader<'info, BondPosition>,
+        #[account(mut)]
+        pub mint: AccountInfo<'info>,
+        #[account(
+            mut,
+            constraint = token_position.mint == mint.key(),
+            constraint = token_position.owner == signer.key(),
+            constraint = token_position.amount == 1,
+        )]
+        pub token_position: Box<Account<'info, TokenAccount>>,
+        #[account(mut, constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_user.mint == mint_staking.key(),
+            constraint = token_staking_user.owner == signer.key(),
+        )]
+        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // bond_exit_early for the holder of a position's token, burning the
+    // token and closing the position
+    pub fn bond_position_exit_early(ctx: Context<BondPositionExitEarly>) -> ProgramResult {
+        let now = unix_now()?;
+        let key: Pubkey;
+        let bump: u8;
+        let amount: u64;
+        let penalty: u64;
+        {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            let bond = ctx.accounts.bond.load_versioned()?;
+            let position = ctx.accounts.position.load()?;
+            key = treasury.key;
+            bump = treasury.bump;
+            require!(bond.exit_penalty > 0, ErrorCode::ExitDisabled);
+
+            let (a, p) = exit_early(&position.bond, bond.exit_penalty, now)?;
+            amount = a;
+            penalty = p;
+
+            emit!(EventBondExit {
+                signer: ctx.accounts.signer.key(),
+                treasury: ctx.accounts.treasury.key(),
+                bond: position.bond.bond,
+                user: ctx.accounts.position.key(),
+                amount,
+                penalty,
+            });
+        }
+
+        if amount > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.token_staking_vesting.to_account_info(),
+                        to: ctx.accounts.token_staking_user.to_account_info(),
+                        authority: ctx.accounts.treasury.to_account_info(),
+                    },
+                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                ),
+                amount,
+            )?;
+        }
+        if penalty > 0 {
+            token::burn(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Burn {
+                        mint: ctx.accounts.mint_staking.to_account_info(),
+                        to: ctx.accounts.token_staking_vesting.to_account_info(),
+                        authority: ctx.accounts.treasury.to_account_info(),
+                    },
+                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                ),
+                penalty,
+            )?;
+        }
+        token::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Burn {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.token_position.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct StakingDeposit<'info> {
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+        // might not exist, locks get no boost then
+        #[account(mut, seeds = [b"lock_curve", treasury.key().as_ref()], bump)]
+        pub lock_curve: AccountInfo<'info>,
+        #[account(mut, has_one = treasury, has_one = signer)]
+        pub user: AccountLoader<'info, User>,
+        #[account(mut, constraint = mint_reserve.key() == treasury.load_versioned()?.mint_reserve)]
+        pub mint_reserve: Box<Account<'info, Mint>>,
+        #[account(mut, constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_reserve_user.mint == mint_reserve.key(),
+            constraint = token_reserve_user.owner == signer.key(),
+        )]
+        pub token_reserve_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_reserve_staking.mint == mint_reserve.key(),
+            constraint = token_reserve_staking.owner == treasury.key(),
+            constraint = token_reserve_staking.key() == treasury.load_versioned()?.token_reserve_staking,
+        )]
+        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_user.mint == mint_staking.key(),
+            constraint = token_staking_user.owner == signer.key(),
+        )]
+        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // with a warmup configured, the deposit only mints staking tokens once
+    // claimed with `staking_claim` after the warmup, earning nothing until then
+    pub fn staking_deposit(ctx: Context<StakingDeposit>, amount: u64) -> ProgramResult {
+        let now = unix_now()?;
+        let key: Pubkey;
+        let bump: u8;
+        let warmup: bool;
+        let mut value: u64 = 0;
+        {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            key = treasury.key;
+            bump = treasury.bump;
+            warmup = treasury.staking_warmup > 0;
+            require!(amount > 0, ErrorCode::InvalidParameter);
+        }
+
+        staking_rebase_mint(
+            &ctx.accounts.treasury,
+            &ctx.accounts.epoch,
+            &ctx.accounts.lock_curve,
+            &ctx.accounts.mint_reserve,
+            &mut ctx.accounts.mint_staking,
+            &mut ctx.accounts.token_reserve_staking,
+            &ctx.accounts.token_staking_vesting,
+            &ctx.accounts.token_program,
+        )?;
+
+        {
+            let treasury = &mut ctx.accounts.treasury.load_mut_versioned()?;
+            if warmup {
+                let user = &mut ctx.accounts.user.load_mut_versioned()?;
+                user.warmup_amount += amount;
+                user.warmup_end = now + treasury.staking_warmup;
+                treasury.staking_warmup_total += amount;
+            } else {
+                value = staking_shares(
+                    amount,
+                    ctx.accounts.mint_staking.supply,
+                    treasury.staking_pool(ctx.accounts.token_reserve_staking.amount),
+                )?;
+            }
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_reserve_user.to_account_info(),
+                    to: ctx.accounts.token_reserve_staking.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        if warmup {
+            return Ok(());
+        }
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.mint_staking.to_account_info(),
+                    to: ctx.accounts.token_staking_user.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                &[&[b"treasury", key.as_ref(), &[bump]]],
+            ),
+            value,
+        )?;
+
+        emit!(EventStakingDeposit {
+            signer: ctx.accounts.signer.key(),
+            treasury: ctx.accounts.treasury.key(),
+            staked: value,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct StakingClaim<'info> {
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+        // might not exist, locks get no boost then
+        #[account(mut, seeds = [b"lock_curve", treasury.key().as_ref()], bump)]
+        pub lock_curve: AccountInfo<'info>,
+        #[account(mut, has_one = treasury, has_one = signer)]
+        pub user: AccountLoader<'info, User>,
+        #[account(mut, constraint = mint_reserve.key() == treasury.load_versioned()?.mint_reserve)]
+        pub mint_reserve: Box<Account<'info, Mint>>,
+        #[account(mut, constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_reserve_staking.mint == mint_reserve.key(),
+            constraint = token_reserve_staking.owner == treasury.key(),
+            constraint = token_reserve_staking.key() == treasury.load_versioned()?.token_reserve_staking,
+        )]
+        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_user.mint == mint_staking.key(),
+            constraint = token_staking_user.owner == signer.key(),
+        )]
+        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn staking_claim(ctx: Context<StakingClaim>) -> ProgramResult {
+        let now = unix_now()?;
+        let key: Pubkey;
+        let bump: u8;
+        let amount: u64;
+        let value: u64;
+        {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            let user = ctx.accounts.user.load_versioned()?;
+            key = treasury.key;
+            bump = treasury.bump;
+            amount = user.warmup_amount;
+            require!(amount > 0, ErrorCode::NothingToClaim);
+            require!(now >= user.warmup_end, ErrorCode::StakingWarmup);
+        }
+
+        staking_rebase_mint(
+            &ctx.accounts.treasury,
+            &ctx.accounts.epoch,
+            &ctx.accounts.lock_curve,
+            &ctx.accounts.mint_reserve,
+            &mut ctx.accounts.mint_staking,
+            &mut ctx.accounts.token_reserve_staking,
+            &ctx.accounts.token_staking_vesting,
+            &ctx.accounts.token_program,
+        )?;
+
+        {
+            let treasury = &mut ctx.accounts.treasury.load_mut_versioned()?;
+            let user = &mut ctx.accounts.user.load_mut_versioned()?;
+            value = staking_shares(
+                amount,
+                ctx.accounts.mint_staking.supply,
+                treasury.staking_pool(ctx.accounts.token_reserve_staking.amount),
+            )?;
+            treasury.staking_warmup_total -= amount;
+            user.warmup_amount = 0;
+            user.warmup_end = 0;
+        }
+
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.mint_staking.to_account_info(),
+                    to: ctx.accounts.token_staking_user.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                &[&[b"treasury", key.as_ref(), &[bump]]],
+            ),
+            value,
+        )?;
+
+        emit!(EventStakingDeposit {
+            signer: ctx.accounts.signer.key(),
+            treasury: ctx.accounts.treasury.key(),
+            staked: value,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct StakingWithdraw<'info> {
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+        // might not exist, locks get no boost then
+        #[account(mut, seeds = [b"lock_curve", treasury.key().as_ref()], bump)]
+        pub lock_curve: AccountInfo<'info>,
+        #[account(mut, has_one = treasury, has_one = signer)]
+        pub user: AccountLoader<'info, User>,
+        #[account(mut, constraint = mint_reserve.key() == treasury.load_versioned()?.mint_reserve)]
+        pub mint_reserve: Box<Account<'info, Mint>>,
+        #[account(mut, constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_reserve_user.mint == mint_reserve.key(),
+            constraint = token_reserve_user.owner == signer.key(),
+        )]
+        pub token_reserve_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_reserve_staking.mint == mint_reserve.key(),
+            constraint = token_reserve_staking.owner == treasury.key(),
+            constraint = token_reserve_staking.key() == treasury.load_versioned()?.token_reserve_staking,
+        )]
+        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_user.mint == mint_staking.key(),
+            constraint = token_staking_user.owner == signer.key(),
+        )]
+        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // with a cooldown configured, the reserve tokens are only transferred
+    // by `staking_redeem` after the cooldown, earning nothing until then
+    pub fn staking_withdraw(ctx: Context<StakingWithdraw>, amount: u64) -> ProgramResult {
+        let now = unix_now()?;
+        let key: Pubkey;
+        let bump: u8;
+        let cooldown: bool;
+        let value: u64;
+        {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            key = treasury.key;
+            bump = treasury.bump;
+            cooldown = treasury.staking_cooldown > 0;
+        }
+
+        staking_rebase_mint(
+            &ctx.accounts.treasury,
+            &ctx.accounts.epoch,
+            &ctx.accounts.lock_curve,
+            &ctx.accounts.mint_reserve,
+            &mut ctx.accounts.mint_staking,
+            &mut ctx.accounts.token_reserve_staking,
+            &ctx.accounts.token_staking_vesting,
+            &ctx.accounts.token_program,
+        )?;
+
+        {
+            let treasury = &mut ctx.accounts.treasury.load_mut_versioned()?;
+            value = staking_value(
+                amount,
+                ctx.accounts.mint_staking.supply,
+                treasury.staking_pool(ctx.accounts.token_reserve_staking.amount),
+            )?;
+            if cooldown {
+                let user = &mut ctx.accounts.user.load_mut_versioned()?;
+                user.cooldown_amount += value;
+                user.cooldown_end = now + treasury.staking_cooldown;
+                treasury.staking_cooldown_total += value;
+            }
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_staking_user.to_account_info(),
+                    to: ctx.accounts.token_staking_vesting.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        token::burn(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Burn {
+                    mint: ctx.accounts.mint_staking.to_account_info(),
+                    to: ctx.accounts.token_staking_vesting.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                &[&[b"treasury", key.as_ref(), &[bump]]],
+            ),
+            amount,
+        )?;
+        if !cooldown {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.token_reserve_staking.to_account_info(),
+                        to: ctx.accounts.token_reserve_user.to_account_info(),
+                        authority: ctx.accounts.treasury.to_account_info(),
+                    },
+                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                ),
+                value,
+            )?;
+        }
+
+        emit!(EventStakingWithdraw {
+            signer: ctx.accounts.signer.key(),
+            treasury: ctx.accounts.treasury.key(),
+            staked: amount,
+            amount: value,
+        });
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct StakingRedeem<'info> {
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury, has_one = signer)]
+        pub user: AccountLoader<'info, User>,
+        #[account(
+            mut,
+            constraint = token_reserve_user.mint == treasury.load_versioned()?.mint_reserve,
+            constraint = token_reserve_user.owner == signer.key(),
+        )]
+        pub token_reserve_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_reserve_staking.owner == treasury.key(),
+            constraint = token_reserve_staking.key() == treasury.load_versioned()?.token_reserve_staking,
+        )]
+        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn staking_redeem(ctx: Context<StakingRedeem>) -> ProgramResult {
+        let now = unix_now()?;
+        let key: Pubkey;
+        let bump: u8;
+        let amount: u64;
+        {
+            let treasury = &mut ctx.accounts.treasury.load_mut_versioned()?;
+            let user = &mut ctx.accounts.user.load_mut_versioned()?;
+            key = treasury.key;
+            bump = treasury.bump;
+            amount = user.cooldown_amount;
+            require!(amount > 0, ErrorCode::NothingToClaim);
+            require!(now >= user.cooldown_end, ErrorCode::StakingCooldown);
+            treasury.staking_cooldown_total -= amount;
+            user.cooldown_amount = 0;
+            user.cooldown_end = 0;
+        }
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_reserve_staking.to_account_info(),
+                    to: ctx.accounts.token_reserve_user.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                &[&[b"treasury", key.as_ref(), &[bump]]],
+            ),
+            amount,
+        )?;
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct StakingRewards<'info> {
+        #[account(mut)]
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+        // might not exist, locks get no boost then
+        #[account(mut, seeds = [b"lock_curve", treasury.key().as_ref()], bump)]
+        pub lock_curve: AccountInfo<'info>,
+        #[account(mut, constraint = mint_reserve.key() == treasury.load_versioned()?.mint_reserve)]
+        pub mint_reserve: Box<Account<'info, Mint>>,
+        #[account(mut, constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_reserve_staking.mint == mint_reserve.key(),
+            constraint = token_reserve_staking.owner == treasury.key(),
+            constraint = token_reserve_staking.key() == treasury.load_versioned()?.token_reserve_staking,
+        )]
+        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // permissionless crank ending the current epoch once it's over
+    pub fn staking_rewards(ctx: Context<StakingRewards>) -> ProgramResult {
+        staking_rebase_mint(
+            &ctx.accounts.treasury,
+            &ctx.accounts.epoch,
+            &ctx.accounts.lock_curve,
+            &ctx.accounts.mint_reserve,
+            &mut ctx.accounts.mint_staking,
+            &mut ctx.accounts.token_reserve_staking,
+            &ctx.accounts.token_staking_vesting,
+            &ctx.accounts.token_program,
+        )
+    }
+
+    #[derive(Accounts)]
+    pub struct ProtocolMetricsView<'info> {
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+        #[account(constraint = mint_reserve.key() == treasury.load_versioned()?.mint_reserve)]
+        pub mint_reserve: Box<Account<'info, Mint>>,
+        #[account(constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            constraint = token_reserve_staking.key() == treasury.load_versioned()?.token_reserve_staking,
+        )]
+        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+    }
+
+    // sets the return data to the borsh serialized ProtocolMetrics
+    pub fn protocol_metrics(ctx: Context<ProtocolMetricsView>) -> ProgramResult {
+        let metrics = math::protocol_metrics(
+            &*ctx.accounts.treasury.load_versioned()?,
+            &*ctx.accounts.epoch.load()?,
+            ctx.accounts.mint_reserve.supply,
+            ctx.accounts.mint_staking.supply,
+            ctx.accounts.token_reserve_staking.amount,
+        )?;
+        set_return_data(&metrics.try_to_vec()?);
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct TreasuryMetadata<'info> {
+        #[account(mut, constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            constraint = mint.key() == treasury.load_versioned()?.mint_reserve
+                || mint.key() == treasury.load_versioned()?.mint_staking
+                @ ErrorCode::InvalidParameter,
+        )]
+        pub mint: AccountInfo<'info>,
+        #[account(mut)]
+        pub metadata: AccountInfo<'info>,
+        #[account(address = token_metadata::ID)]
+        pub metadata_program: AccountInfo<'info>,
+        pub rent: Sysvar<'info, Rent>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // creates the metaplex metadata of the reserve or staking mint, or
+    // updates it once it exists, with the treasury as mint and update
+    // authority
+    pub fn treasury_metadata(
+        ctx: Context<TreasuryMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> ProgramResult {
+        let (key, bump) = {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            (treasury.key, treasury.bump)
+        };
+        let metadata = Pubkey::find_program_address(
+            &[
+                b"metadata",
+                token_metadata::ID.as_ref(),
+                ctx.accounts.mint.key().as_ref(),
+            ],
+            &token_metadata::ID,
+        )
+        .0;
+        require!(
+            ctx.accounts.metadata.key() == metadata,
+            ErrorCode::InvalidParameter
+        );
+
+        let data = MetadataDataV2 {
+            name,
+            symbol,
+            uri,
+            seller_fee_basis_points: 0,
+            creators: None,
+            collection: None,
+            uses: None,
+        };
+        let treasury = ctx.accounts.treasury.key();
+        let ix = if ctx.accounts.metadata.data_is_empty() {
+            let mut args = vec![METADATA_CREATE_V3];
+            MetadataCreateV3 {
+                data,
+                is_mutable: true,
+                collection_details: None,
+            }
+            .serialize(&mut args)?;
+            Instruction {
+                program_id: token_metadata::ID,
+                accounts: vec![
+                    AccountMeta::new(metadata, false),
+                    AccountMeta::new_readonly(ctx.accounts.mint.key(), false),
+                    // mint authority, payer then update authority
+                    AccountMeta::new_readonly(treasury, true),
+                    AccountMeta::new(ctx.accounts.signer.key(), true),
+                    AccountMeta::new_readonly(treasury, true),
+                    AccountMeta::new_readonly(ctx.accounts.system_program.key(), false),
+                    AccountMeta::new_readonly(ctx.accounts.rent.key(), false),
+                ],
+                data: args,
+            }
+        } else {
+            let mut args = vec![METADATA_UPDATE_V2];
+            MetadataUpdateV2 {
+                data: Some(data),
+                update_authority: None,
+                primary_sale_happened: None,
+                is_mutable: None,
+            }
+            .serialize(&mut args)?;
+            Instruction {
+                program_id: token_metadata::ID,
+                accounts: vec![
+                    AccountMeta::new(metadata, false),
+                    AccountMeta::new_readonly(treasury, true),
+                ],
+                data: args,
+            }
+        };
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.metadata.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.rent.to_account_info(),
+                ctx.accounts.metadata_program.to_account_info(),
+            ],
+            &[&[b"treasury", key.as_ref(), &[bump]]],
+        )
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct OracleInitialize<'info> {
+        #[account(mut, constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"oracle", treasury.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<Oracle>(),
+        )]
+        pub oracle: AccountLoader<'info, Oracle>,
+        pub source: AccountInfo<'info>,
+        pub source_quote: AccountInfo<'info>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn oracle_initialize(
+        ctx: Context<OracleInitialize>,
+        bump: u8,
+        kind: u64,
+        quote_decimals: u64,
+        max_age: u64,
+        window: u64,
+        interval: u64,
+    ) -> ProgramResult {
+        let oracle = &mut ctx.accounts.oracle.load_init()?;
+        oracle.bump = bump;
+        oracle.treasury = ctx.accounts.treasury.key();
+        oracle_apply(
+            oracle,
+            &*ctx.accounts.treasury.load_versioned()?,
+            &ctx.accounts.source,
+            &ctx.accounts.source_quote,
+            ctx.program_id,
+            kind,
+            quote_decimals,
+            max_age,
+            window,
+            interval,
+        )
+    }
+
+    #[derive(Accounts)]
+    pub struct OracleConfigure<'info> {
+        #[account(constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub oracle: AccountLoader<'info, Oracle>,
+        pub source: AccountInfo<'info>,
+        pub source_quote: AccountInfo<'info>,
+    }
+
+    pub fn oracle_configure(
+        ctx: Context<OracleConfigure>,
+        kind: u64,
+        quote_decimals: u64,
+        max_age: u64,
+        window: u64,
+        interval: u64,
+    ) -> ProgramResult {
+        oracle_apply(
+            &mut *ctx.accounts.oracle.load_mut()?,
+            &*ctx.accounts.treasury.load_versioned()?,
+            &ctx.accounts.source,
+            &ctx.accounts.source_quote,
+            ctx.program_id,
+            kind,
+            quote_decimals,
+            max_age,
+            window,
+            interval,
+        )
+    }
+
+    #[derive(Accounts)]
+    pub struct OracleUpdate<'info> {
+        #[account(mut, has_one = source, has_one = source_quote)]
+        pub oracle: AccountLoader<'info, Oracle>,
+        pub source: AccountInfo<'info>,
+        pub source_quote: AccountInfo<'info>,
+    }
+
+    // permissionless, meant to come right before deposits in the same
+    // transaction. Pools are read at most once per interval, count the price
+    // read now from the next update on, and set a new price once a window
+    // has passed
+    pub fn oracle_update(ctx: Context<OracleUpdate>) -> ProgramResult {
+        let oracle = &mut ctx.accounts.oracle.load_mut()?;
+        if oracle.kind == ORACLE_FEED {
+            let feed = AccountLoader::<PriceFeed>::try_from(&ctx.accounts.source)?;
+            let feed = feed.load()?;
+            oracle.price = feed.price;
+            oracle.updated = feed.updated;
+            return Ok(());
+        }
+        let now = unix_now()?;
+        if oracle.spot_updated > 0 && now < oracle.spot_updated + oracle.interval {
+            return Ok(());
+        }
+        let reserve = Account::<TokenAccount>::try_from(&ctx.accounts.source)?;
+        let quote = Account::<TokenAccount>::try_from(&ctx.accounts.source_quote)?;
+        require!(reserve.amount > 0, ErrorCode::InvalidParameter);
+        let quote_amount = muldiv(quote.amount, ONE, 10_u64.pow(oracle.quote_decimals as u32))?;
+        if oracle.spot_updated == 0 {
+            oracle.window_start = now;
+        } else {
+            oracle.price_cumulative +=
+                oracle.spot as u128 * now.saturating_sub(oracle.spot_updated) as u128;
+        }
+        oracle.spot = muldiv(quote_amount, ONE, reserve.amount)?;
+        oracle.spot_updated = now;
+        let elapsed = now.saturating_sub(oracle.window_start);
+        if elapsed >= oracle.window {
+            oracle.price =
+                ((oracle.price_cumulative - oracle.window_cumulative) / elapsed as u128) as u64;
+            oracle.updated = now;
+            oracle.window_start = now;
+            oracle.window_cumulative = oracle.price_cumulative;
+        }
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct PriceFeedInitialize<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"price_feed", signer.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<PriceFeed>(),
+        )]
+        pub feed: AccountLoader<'info, PriceFeed>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn price_feed_initialize(ctx: Context<PriceFeedInitialize>, bump: u8) -> ProgramResult {
+        let feed = &mut ctx.accounts.feed.load_init()?;
+        feed.bump = bump;
+        feed.authority = ctx.accounts.signer.key();
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct PriceFeedSet<'info> {
+        #[account(constraint = signer.key() == feed.load()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub feed: AccountLoader<'info, PriceFeed>,
+    }
+
+    pub fn price_feed_set(ctx: Context<PriceFeedSet>, price: u64) -> ProgramResult {
+        let feed = &mut ctx.accounts.feed.load_mut()?;
+        feed.price = price;
+        feed.updated = unix_now()?;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct LockInitialize<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"lock", treasury.key().as_ref(), signer.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<Lock>(),
+        )]
+        pub lock: AccountLoader<'info, Lock>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn lock_initialize(ctx: Context<LockInitialize>, bump: u8) -> ProgramResult {
+        let lock = &mut ctx.accounts.lock.load_init()?;
+        lock.bump = bump;
+        lock.treasury = ctx.accounts.treasury.key();
+        lock.signer = ctx.accounts.signer.key();
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct LockCurveInitialize<'info> {
+        #[account(mut, constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"lock_curve", treasury.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<LockCurve>(),
+        )]
+        pub lock_curve: AccountLoader<'info, LockCurve>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // locks need it, its period is sized for locks of up to the current
+    // lock_max_duration and can't change
+    pub fn lock_curve_initialize(ctx: Context<LockCurveInitialize>, bump: u8) -> ProgramResult {
+        let max_duration = ctx.accounts.treasury.load_versioned()?.lock_max_duration;
+        require!(max_duration > 0, ErrorCode::LockDurationInvalid);
+        let curve = &mut ctx.accounts.lock_curve.load_init()?;
+        curve.bump = bump;
+        curve.treasury = ctx.accounts.treasury.key();
+        curve.period = (max_duration - 1) / LOCK_CURVE_HORIZON + 1;
+        curve.next = unix_now()? / curve.period + 1;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct LockDeposit<'info> {
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub lock_curve: AccountLoader<'info, LockCurve>,
+        #[account(mut, has_one = treasury, has_one = signer)]
+        pub lock: AccountLoader<'info, Lock>,
+        #[account(constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_staking_user.mint == mint_staking.key(),
+            constraint = token_staking_user.owner == signer.key(),
+        )]
+        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // locks `amount` more staking tokens until `end` (rounded down to the
+    // lock curve's period), which can only be pushed back (`amount` can be 0
+    // to only extend the lock)
+    pub fn lock_deposit(ctx: Context<LockDeposit>, amount: u64, end: u64) -> ProgramResult {
+        let now = unix_now()?;
+        let end = {
+            let period = ctx.accounts.lock_curve.load()?.period;
+            end / period * period
+        };
+        let weight: u64;
+        {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            let curve = &mut ctx.accounts.lock_curve.load_mut()?;
+            let lock = &mut ctx.accounts.lock.load_mut()?;
+            let max_duration = treasury.lock_max_duration;
+            require!(
+                max_duration > 0
+                    && end > now
+                    && end <= now + max_duration.min(LOCK_CURVE_HORIZON * curve.period)
+                    && end >= lock.end,
+                ErrorCode::LockDurationInvalid
+            );
+            require!(amount > 0 || lock.amount > 0, ErrorCode::InvalidParameter);
+
+            lock_update(lock, curve, now)?;
+            if !curve.ended(lock.end) {
+                curve.remove(lock.slope, lock.end);
+            }
+            if lock.amount == 0 {
+                lock.start = now;
+            }
+            lock.amount += amount;
+            lock.end = end;
+            lock.updated = now;
+            lock.slope = lock_slope(lock.amount, max_duration);
+            lock.reward_per_weight_paid = curve.reward_per_weight;
+            lock.reward_per_weight_time_paid = curve.reward_per_weight_time;
+            curve.add(lock.slope, lock.end)?;
+            lock.settled = 0;
+            weight = lock.voting_weight(now)?;
+        }
+
+        if amount > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.token_staking_user.to_account_info(),
+                        to: ctx.accounts.token_staking_vesting.to_account_info(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        }
+
+        emit!(EventLockDeposit {
+            signer: ctx.accounts.signer.key(),
+            treasury: ctx.accounts.treasury.key(),
+            amount,
+            end,
+            weight,
+        });
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct LockRefresh<'info> {
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub lock_curve: AccountLoader<'info, LockCurve>,
+        #[account(mut, has_one = treasury)]
+        pub lock: AccountLoader<'info, Lock>,
+    }
+
+    // permissionless, moves the boost a lock earned so far into its pending
+    // amount. Locks earn without it, but an ended lock has to settle before
+    // its lock curve period entry can be reused for new locks, which this
+    // lets anyone do for locks left alone that long
+    pub fn lock_refresh(ctx: Context<LockRefresh>) -> ProgramResult {
+        let curve = &mut ctx.accounts.lock_curve.load_mut()?;
+        let lock = &mut ctx.accounts.lock.load_mut()?;
+        lock_update(lock, curve, unix_now()?)?;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct LockClaim<'info> {
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub lock_curve: AccountLoader<'info, LockCurve>,
+        #[account(mut, has_one = treasury, has_one = signer)]
+        pub lock: AccountLoader<'info, Lock>,
+        #[account(constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
+        pub mint_staking: Box<Account<'info, Mint>>,
+        #[account(
+            mut,
+            constraint = token_staking_user.mint == mint_staking.key(),
+            constraint = token_staking_user.owner == signer.key(),
+        )]
+        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn lock_claim(ctx: Context<LockClaim>) -> ProgramResult {
+        let key: Pubkey;
+        let bump: u8;
+        let amount: u64;
+        {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            let curve = &mut ctx.accounts.lock_curve.load_mut()?;
+            let lock = &mut ctx.accounts.lock.load_mut()?;
+            key = treasury.key;
+            bump = treasury.bump;
+            lock_update(lock, curve, unix_now()?)?;
+            amount = lock.pending;
+            require!(amount > 0, ErrorCode::NothingToClaim);
+            lock.pending = 0;
+        }
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_staking_vesting.to_account_info(),
+                    to: ctx.accounts.token_staking_user.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                &[&[b"treasury", key.as_ref(), &[bump]]],
+            ),
+            amount,
+        )?;
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct LockWithdraw<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub lock_curve: AccountLoader<'info, LockCurve>,
+        #[account(mut, has_one = treasury, has_one = signer, close = signer)]
+        pub lock: AccountLoader<'info, Lock>,
+        #[account(constraint = mint_staking.key() == treasury.load_versioned()?.mint_staking)]
         pub mint_staking: Box<Account<'info, Mint>>,
         #[account(
             mut,
-            constraint = token_staking_vesting.mint == mint_staking.key(),
-            constraint = token_staking_vesting.owner == treasury.key(),
-            constraint = token_staking_vesting.key() == treasury.load()?.token_staking_vesting,
+            constraint = token_staking_user.mint == mint_staking.key(),
+            constraint = token_staking_user.owner == signer.key(),
+        )]
+        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_staking_vesting.mint == mint_staking.key(),
+            constraint = token_staking_vesting.owner == treasury.key(),
+            constraint = token_staking_vesting.key() == treasury.load_versioned()?.token_staking_vesting,
+        )]
+        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // returns the locked staking tokens and unclaimed boost once the lock
+    // is over, closing the lock
+    pub fn lock_withdraw(ctx: Context<LockWithdraw>) -> ProgramResult {
+        let now = unix_now()?;
+        let key: Pubkey;
+        let bump: u8;
+        let amount: u64;
+        let boost: u64;
+        {
+            let treasury = ctx.accounts.treasury.load_versioned()?;
+            let curve = &mut ctx.accounts.lock_curve.load_mut()?;
+            let lock = &mut ctx.accounts.lock.load_mut()?;
+            key = treasury.key;
+            bump = treasury.bump;
+            require!(now >= lock.end, ErrorCode::LockNotOver);
+            lock_update(lock, curve, now)?;
+            amount = lock.amount;
+            boost = lock.pending;
+        }
+
+        if amount + boost > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.token_staking_vesting.to_account_info(),
+                        to: ctx.accounts.token_staking_user.to_account_info(),
+                        authority: ctx.accounts.treasury.to_account_info(),
+                    },
+                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                ),
+                amount + boost,
+            )?;
+        }
+
+        emit!(EventLockWithdraw {
+            signer: ctx.accounts.signer.key(),
+            treasury: ctx.accounts.treasury.key(),
+            amount,
+            boost,
+        });
+
+        Ok(())
+    }
+    #[derive(Accounts)]
+    #[instruction(bump: u8, key: Pubkey)]
+    pub struct ProposalCreate<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+        #[account(has_one = treasury, has_one = signer)]
+        pub lock: AccountLoader<'info, Lock>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"proposal", treasury.key().as_ref(), key.as_ref()],
+            bump = bump,
+            space = 8 + size_of::<Proposal>() + PROPOSAL_MAX_ACTIONS * size_of::<ProposalAction>(),
+        )]
+        pub proposal: Account<'info, Proposal>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // any lock holder can propose, `key` is any pubkey the proposal pda is
+    // derived from. voting lasts until the end of the next epoch
+    pub fn proposal_create(
+        ctx: Context<ProposalCreate>,
+        bump: u8,
+        _key: Pubkey,
+        actions: Vec<ProposalAction>,
+    ) -> ProgramResult {
+        let now = unix_now()?;
+        let treasury = ctx.accounts.treasury.load_versioned()?;
+        let epoch = ctx.accounts.epoch.load()?;
+        let lock = ctx.accounts.lock.load()?;
+        require!(
+            treasury.governance_quorum > 0,
+            ErrorCode::GovernanceDisabled
+        );
+        require!(
+            !actions.is_empty() && actions.len() <= PROPOSAL_MAX_ACTIONS,
+            ErrorCode::InvalidParameter
+        );
+        require!(lock.voting_weight(now)? > 0, ErrorCode::Unauthorized);
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.bump = bump;
+        proposal.treasury = ctx.accounts.treasury.key();
+        proposal.proposer = ctx.accounts.signer.key();
+        proposal.start = now;
+        proposal.end = epoch.end.max(now) + epoch.length;
+        proposal.quorum = treasury.governance_quorum;
+        proposal.threshold = treasury.governance_threshold;
+        proposal.actions = actions;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct ProposalVoteCast<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub proposal: Account<'info, Proposal>,
+        #[account(has_one = treasury, has_one = signer)]
+        pub lock: AccountLoader<'info, Lock>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"vote", proposal.key().as_ref(), lock.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<ProposalVote>(),
+        )]
+        pub vote: AccountLoader<'info, ProposalVote>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // votes with the lock's weight as of the proposal start, locks changed
+    // since can't vote so tokens can't be moved to vote again
+    pub fn proposal_vote(ctx: Context<ProposalVoteCast>, bump: u8, support: bool) -> ProgramResult {
+        let now = unix_now()?;
+        let lock = ctx.accounts.lock.load()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(now < proposal.end, ErrorCode::ProposalVotingOver);
+        require!(lock.updated < proposal.start, ErrorCode::LockNotEligible);
+        let weight = lock.voting_weight(proposal.start)?;
+        require!(weight > 0, ErrorCode::LockNotEligible);
+
+        if support {
+            proposal.votes_for += weight;
+        } else {
+            proposal.votes_against += weight;
+        }
+
+        let vote = &mut ctx.accounts.vote.load_init()?;
+        vote.bump = bump;
+        vote.proposal = proposal.key();
+        vote.lock = ctx.accounts.lock.key();
+        vote.weight = weight;
+        vote.support = support as u8;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct ProposalExecute<'info> {
+        #[account(mut)]
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+        #[account(mut, has_one = treasury)]
+        pub proposal: Account<'info, Proposal>,
+    }
+
+    // permissionless once voting is over, bonds configured by the proposal
+    // are passed as remaining accounts, with the role of the treasury's
+    // address if there are any
+    pub fn proposal_execute<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProposalExecute<'info>>,
+    ) -> ProgramResult {
+        let now = unix_now()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(now >= proposal.end, ErrorCode::ProposalVotingNotOver);
+        require!(!proposal.executed, ErrorCode::ProposalExecuted);
+        let votes = proposal.votes_for + proposal.votes_against;
+        require!(
+            votes >= proposal.quorum
+                && proposal.votes_for >= muldiv(votes, proposal.threshold, ONE)?,
+            ErrorCode::ProposalRejected
+        );
+        proposal.executed = true;
+
+        for action in proposal.actions.iter() {
+            match action {
+                ProposalAction::TreasuryConfigure { params } => {
+                    treasury_apply(&mut *ctx.accounts.treasury.load_mut_versioned()?, params)?
+                }
+                ProposalAction::EpochConfigure { params } => {
+                    epoch_apply(&mut *ctx.accounts.epoch.load_mut()?, params)?
+                }
+                ProposalAction::GovernanceConfigure { params } => {
+                    governance_apply(&mut *ctx.accounts.treasury.load_mut_versioned()?, params)?
+                }
+                ProposalAction::BondConfigure { bond, params } => {
+                    let info = ctx
+                        .remaining_accounts
+                        .iter()
+                        .find(|a| a.key == bond)
+                        .ok_or(ErrorCode::InvalidParameter)?;
+                    let loader = AccountLoader::<Bond>::try_from(info)?;
+                    let bond = &mut loader.load_mut_versioned()?;
+                    require!(
+                        bond.treasury == ctx.accounts.treasury.key(),
+                        ErrorCode::InvalidParameter
+                    );
+                    // needs the bond manager role like bond_configure, held by
+                    // the treasury's own address, its role passed along
+                    let treasury = ctx.accounts.treasury.key();
+                    let allowed = ctx.remaining_accounts.iter().any(|info| {
+                        AccountLoader::<Role>::try_from(info)
+                            .and_then(|role| {
+                                Ok(role.load()?.allows(&treasury, &treasury, ROLE_BOND_MANAGER))
+                            })
+                            .unwrap_or(false)
+                    });
+                    require!(allowed, ErrorCode::Unauthorized);
+                    bond_apply(bond, params)?
+                }
+            }
+        }
+        Ok(())
+    }
+    #[derive(Accounts)]
+    #[instruction(bump: u8, number: u64)]
+    pub struct GaugeVoteCast<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+        #[account(has_one = treasury, has_one = signer)]
+        pub lock: AccountLoader<'info, Lock>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"gauge_vote", lock.key().as_ref(), number.to_le_bytes().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<GaugeVote>(),
+        )]
+        pub gauge_vote: AccountLoader<'info, GaugeVote>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // splits the lock's current weight across the bonds passed in
+    // remaining accounts, `weights` being 1e9 percents of it. votes count
+    // toward the capacity of bonds in the next epoch
+    pub fn gauge_vote<'info>(
+        ctx: Context<'_, '_, '_, 'info, GaugeVoteCast<'info>>,
+        bump: u8,
+        number: u64,
+        weights: Vec<u64>,
+    ) -> ProgramResult {
+        let now = unix_now()?;
+        let epoch = &mut ctx.accounts.epoch.load_mut()?;
+        let lock = ctx.accounts.lock.load()?;
+        require!(epoch.gauge_budget > 0, ErrorCode::GaugesDisabled);
+        require!(number == epoch.number, ErrorCode::InvalidParameter);
+        require!(
+            !weights.is_empty() && weights.len() == ctx.remaining_accounts.len(),
+            ErrorCode::InvalidParameter
+        );
+        require!(
+            weights.iter().sum::<u64>() <= ONE,
+            ErrorCode::InvalidParameter
+        );
+        let weight = lock.voting_weight(now)?;
+        require!(weight > 0, ErrorCode::LockNotEligible);
+
+        let mut total = 0;
+        for (info, w) in ctx.remaining_accounts.iter().zip(weights.iter()) {
+            let loader = AccountLoader::<Bond>::try_from(info)?;
+            let bond = &mut loader.load_mut_versioned()?;
+            require!(
+                bond.treasury == ctx.accounts.treasury.key(),
+                ErrorCode::InvalidParameter
+            );
+            bond_gauge_roll(bond, epoch.number);
+            let votes = muldiv(weight, *w, ONE)?;
+            bond.gauge_votes += votes;
+            total += votes;
+        }
+        epoch.gauge_votes += total;
+
+        let gauge_vote = &mut ctx.accounts.gauge_vote.load_init()?;
+        gauge_vote.bump = bump;
+        gauge_vote.lock = ctx.accounts.lock.key();
+        gauge_vote.epoch = number;
+        gauge_vote.weight = total;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct BondGaugeApply<'info> {
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(has_one = treasury)]
+        pub epoch: AccountLoader<'info, Epoch>,
+        #[account(mut, has_one = treasury)]
+        pub bond: AccountLoader<'info, Bond>,
+    }
+
+    // permissionless crank setting a bond's max_debt to its share of the
+    // gauge budget by the votes of the previous epoch, run after each rebase
+    pub fn bond_gauge_apply(ctx: Context<BondGaugeApply>) -> ProgramResult {
+        let epoch = ctx.accounts.epoch.load()?;
+        let bond = &mut ctx.accounts.bond.load_mut_versioned()?;
+        require!(epoch.gauge_budget > 0, ErrorCode::GaugesDisabled);
+        bond_gauge_roll(bond, epoch.number);
+        if epoch.gauge_votes_last > 0 {
+            bond.max_debt = muldiv(
+                epoch.gauge_budget,
+                bond.gauge_votes_last,
+                epoch.gauge_votes_last,
+            )?;
+        }
+        Ok(())
+    }
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct MultisigCreate<'info> {
+        #[account(mut, constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"multisig", treasury.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<Multisig>() + MULTISIG_MAX_OWNERS * size_of::<Pubkey>(),
+        )]
+        pub multisig: Account<'info, Multisig>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // the multisig only acts once its signer is made treasury authority
+    // (and granted roles), the signer pays for accounts it creates so it
+    // needs to be funded
+    pub fn multisig_create(
+        ctx: Context<MultisigCreate>,
+        bump: u8,
+        owners: Vec<Pubkey>,
+        threshold: u64,
+    ) -> ProgramResult {
+        multisig_owners_check(&owners, threshold)?;
+        let multisig_key = ctx.accounts.multisig.key();
+        let (_, signer_bump) = Pubkey::find_program_address(
+            &[b"multisig_signer", multisig_key.as_ref()],
+            ctx.program_id,
+        );
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.bump = bump;
+        multisig.signer_bump = signer_bump;
+        multisig.treasury = ctx.accounts.treasury.key();
+        multisig.threshold = threshold;
+        multisig.owners = owners;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct MultisigConfigure<'info> {
+        #[account(mut)]
+        pub multisig: Account<'info, Multisig>,
+        #[account(
+            seeds = [b"multisig_signer", multisig.key().as_ref()],
+            bump = multisig.signer_bump,
+        )]
+        pub multisig_signer: Signer<'info>,
+    }
+
+    // only callable through an executed multisig transaction
+    pub fn multisig_configure(
+        ctx: Context<MultisigConfigure>,
+        owners: Vec<Pubkey>,
+        threshold: u64,
+    ) -> ProgramResult {
+        multisig_owners_check(&owners, threshold)?;
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.threshold = threshold;
+        multisig.owners = owners;
+        multisig.owner_set_seqno += 1;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8, key: Pubkey, metas: Vec<MultisigAccount>, data: Vec<u8>)]
+    pub struct MultisigTransactionCreate<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub multisig: Account<'info, Multisig>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"multisig_transaction", multisig.key().as_ref(), key.as_ref()],
+            bump = bump,
+            space = 8
+                + size_of::<MultisigTransaction>()
+                + metas.len() * size_of::<MultisigAccount>()
+                + data.len()
+                + multisig.owners.len(),
+        )]
+        pub transaction: Account<'info, MultisigTransaction>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // proposes a reserve program instruction, approved by its proposer.
+    // `key` is any pubkey the transaction pda is derived from
+    pub fn multisig_transaction_create(
+        ctx: Context<MultisigTransactionCreate>,
+        bump: u8,
+        _key: Pubkey,
+        metas: Vec<MultisigAccount>,
+        data: Vec<u8>,
+    ) -> ProgramResult {
+        let multisig = &ctx.accounts.multisig;
+        let index = multisig
+            .owners
+            .iter()
+            .position(|o| *o == ctx.accounts.signer.key())
+            .ok_or(ErrorCode::MultisigNotOwner)?;
+
+        let transaction = &mut ctx.accounts.transaction;
+        transaction.bump = bump;
+        transaction.multisig = multisig.key();
+        transaction.proposer = ctx.accounts.signer.key();
+        transaction.metas = metas;
+        transaction.data = data;
+        transaction.approvals = vec![false; multisig.owners.len()];
+        transaction.approvals[index] = true;
+        transaction.owner_set_seqno = multisig.owner_set_seqno;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct MultisigApprove<'info> {
+        pub signer: Signer<'info>,
+        pub multisig: Account<'info, Multisig>,
+        #[account(mut, has_one = multisig)]
+        pub transaction: Account<'info, MultisigTransaction>,
+    }
+
+    pub fn multisig_approve(ctx: Context<MultisigApprove>) -> ProgramResult {
+        let signer = ctx.accounts.signer.key();
+        let multisig = &ctx.accounts.multisig;
+        let transaction = &mut ctx.accounts.transaction;
+        require!(
+            transaction.owner_set_seqno == multisig.owner_set_seqno,
+            ErrorCode::MultisigOwnersChanged
+        );
+        require!(
+            !transaction.executed,
+            ErrorCode::MultisigTransactionExecuted
+        );
+        let index = multisig
+            .owners
+            .iter()
+            .position(|o| *o == signer)
+            .ok_or(ErrorCode::MultisigNotOwner)?;
+        transaction.approvals[index] = true;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct MultisigExecute<'info> {
+        pub multisig: Account<'info, Multisig>,
+        #[account(mut, has_one = multisig)]
+        pub transaction: Account<'info, MultisigTransaction>,
+    }
+
+    // permissionless once approved, the accounts of the transaction's
+    // instruction (and the reserve program) are passed as remaining accounts
+    pub fn multisig_execute<'info>(
+        ctx: Context<'_, '_, '_, 'info, MultisigExecute<'info>>,
+    ) -> ProgramResult {
+        let multisig = &ctx.accounts.multisig;
+        let transaction = &mut ctx.accounts.transaction;
+        require!(
+            transaction.owner_set_seqno == multisig.owner_set_seqno,
+            ErrorCode::MultisigOwnersChanged
+        );
+        require!(
+            !transaction.executed,
+            ErrorCode::MultisigTransactionExecuted
+        );
+        let approvals = transaction.approvals.iter().filter(|a| **a).count() as u64;
+        require!(
+            approvals >= multisig.threshold,
+            ErrorCode::MultisigThresholdNotReached
+        );
+        transaction.executed = true;
+
+        let multisig_key = multisig.key();
+        let signer = Pubkey::create_program_address(
+            &[
+                b"multisig_signer",
+                multisig_key.as_ref(),
+                &[multisig.signer_bump],
+            ],
+            ctx.program_id,
+        )
+        .map_err(|_| ErrorCode::InvalidParameter)?;
+        let ix = Instruction {
+            program_id: *ctx.program_id,
+            accounts: transaction
+                .metas
+                .iter()
+                .map(|m| AccountMeta {
+                    pubkey: m.pubkey,
+                    is_signer: m.is_signer || m.pubkey == signer,
+                    is_writable: m.is_writable,
+                })
+                .collect(),
+            data: transaction.data.clone(),
+        };
+        invoke_signed(
+            &ix,
+            ctx.remaining_accounts,
+            &[&[
+                b"multisig_signer",
+                multisig_key.as_ref(),
+                &[multisig.signer_bump],
+            ]],
+        )?;
+        Ok(())
+    }
+    #[derive(Accounts)]
+    #[instruction(bump: u8)]
+    pub struct MarketConfigInitialize<'info> {
+        #[account(mut, constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"market_config", treasury.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<MarketConfig>(),
+        )]
+        pub market_config: AccountLoader<'info, MarketConfig>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn market_config_initialize(
+        ctx: Context<MarketConfigInitialize>,
+        bump: u8,
+    ) -> ProgramResult {
+        let market_config = &mut ctx.accounts.market_config.load_init()?;
+        market_config.bump = bump;
+        market_config.treasury = ctx.accounts.treasury.key();
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct MarketConfigConfigure<'info> {
+        #[account(constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub market_config: AccountLoader<'info, MarketConfig>,
+    }
+
+    pub fn market_config_configure(ctx: Context<MarketConfigConfigure>, fee: u64) -> ProgramResult {
+        require!(fee <= ONE, ErrorCode::InvalidParameter);
+        let market_config = &mut ctx.accounts.market_config.load_mut()?;
+        market_config.fee = fee;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8, token_payout_bump: u8, key: Pubkey)]
+    pub struct MarketCreate<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"market", treasury.key().as_ref(), key.as_ref()],
+            bump = bump,
+            space = 8 + size_of::<Market>(),
+        )]
+        pub market: AccountLoader<'info, Market>,
+        pub mint_quote: Box<Account<'info, Mint>>,
+        pub mint_payout: Box<Account<'info, Mint>>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"market_token_payout", market.key().as_ref()],
+            bump = token_payout_bump,
+            owner = token::ID,
+            space = TokenAccount::LEN
+        )]
+        pub token_payout: AccountInfo<'info>,
+        #[account(constraint = token_quote.mint == mint_quote.key())]
+        pub token_quote: Box<Account<'info, TokenAccount>>,
+        pub rent: Sysvar<'info, Rent>,
+        pub token_program: Program<'info, Token>,
+        pub system_program: Program<'info, System>,
+    }
+
+    // anyone can open a market selling their own token. it's funded by
+    // transferring payout tokens to token_payout
+    pub fn market_create(
+        ctx: Context<MarketCreate>,
+        bump: u8,
+        _token_payout_bump: u8,
+        key: Pubkey,
+    ) -> ProgramResult {
+        token::initialize_account(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::InitializeAccount {
+                account: ctx.accounts.token_payout.to_account_info(),
+                mint: ctx.accounts.mint_payout.to_account_info(),
+                authority: ctx.accounts.market.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+        ))?;
+
+        let market = &mut ctx.accounts.market.load_init()?;
+        market.bump = bump;
+        market.treasury = ctx.accounts.treasury.key();
+        market.key = key;
+        market.issuer = ctx.accounts.signer.key();
+        market.mint_quote = ctx.accounts.mint_quote.key();
+        market.mint_payout = ctx.accounts.mint_payout.key();
+        market.token_payout = ctx.accounts.token_payout.key();
+        market.token_quote = ctx.accounts.token_quote.key();
+        market.quote_decimals = ctx.accounts.mint_quote.decimals;
+        market.payout_decimals = ctx.accounts.mint_payout.decimals;
+        market.total_debt_last = unix_now()?;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct MarketConfigure<'info> {
+        #[account(constraint = signer.key() == market.load()?.issuer @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        #[account(mut)]
+        pub market: AccountLoader<'info, Market>,
+    }
+
+    // same parameters as bond_configure, without the fee
+    pub fn market_configure(
+        ctx: Context<MarketConfigure>,
+        vesting_period: u64,
+        rfv_rate: u64,
+        min_price: u64,
+        max_payout: u64,
+        max_debt: u64,
+        bcv: u64,
+    ) -> ProgramResult {
+        require!(vesting_period >= 3600, ErrorCode::InvalidParameter);
+        let market = &mut ctx.accounts.market.load_mut()?;
+        market.vesting_period = vesting_period;
+        market.rfv_rate = rfv_rate;
+        market.min_price = min_price;
+        market.max_payout = max_payout;
+        market.max_debt = max_debt;
+        market.bcv = bcv;
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct MarketWithdraw<'info> {
+        #[account(constraint = signer.key() == market.load()?.issuer @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub market: AccountLoader<'info, Market>,
+        #[account(mut, constraint = token_payout.key() == market.load()?.token_payout)]
+        pub token_payout: Box<Account<'info, TokenAccount>>,
+        #[account(mut, constraint = token_payout_issuer.mint == token_payout.mint)]
+        pub token_payout_issuer: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    // returns unsold payout tokens to the issuer, tokens owed to buyers
+    // stay in the vault
+    pub fn market_withdraw(ctx: Context<MarketWithdraw>, amount: u64) -> ProgramResult {
+        let treasury: Pubkey;
+        let key: Pubkey;
+        let bump: u8;
+        {
+            let market = ctx.accounts.market.load()?;
+            require!(
+                amount <= ctx.accounts.token_payout.amount.saturating_sub(market.owed),
+                ErrorCode::MarketCapacityExceeded
+            );
+            treasury = market.treasury;
+            key = market.key;
+            bump = market.bump;
+        }
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_payout.to_account_info(),
+                    to: ctx.accounts.token_payout_issuer.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
+                },
+                &[&[b"market", treasury.as_ref(), key.as_ref(), &[bump]]],
+            ),
+            amount,
+        )?;
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    #[instruction(bump: u8, key: Pubkey)]
+    pub struct MarketDeposit<'info> {
+        #[account(mut)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(has_one = treasury)]
+        pub market_config: AccountLoader<'info, MarketConfig>,
+        #[account(mut, has_one = treasury)]
+        pub market: AccountLoader<'info, Market>,
+        #[account(
+            init,
+            payer = signer,
+            seeds = [b"market_position", market.key().as_ref(), key.as_ref()],
+            bump = bump,
+            space = 8 + size_of::<MarketPosition>(),
+        )]
+        pub position: AccountLoader<'info, MarketPosition>,
+        #[account(constraint = mint_payout.key() == market.load()?.mint_payout)]
+        pub mint_payout: Box<Account<'info, Mint>>,
+        #[account(constraint = token_payout.key() == market.load()?.token_payout)]
+        pub token_payout: Box<Account<'info, TokenAccount>>,
+        #[account(
+            mut,
+            constraint = token_quote_user.mint == market.load()?.mint_quote,
+            constraint = token_quote_user.owner == signer.key(),
         )]
-        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub token_quote_user: Box<Account<'info, TokenAccount>>,
+        #[account(mut, constraint = token_quote_issuer.key() == market.load()?.token_quote)]
+        pub token_quote_issuer: Box<Account<'info, TokenAccount>>,
         #[account(
             mut,
-            constraint = token_staking_user.mint == mint_staking.key(),
-            constraint = token_staking_user.owner == signer.key(),
+            constraint = token_quote_treasury.mint == market.load()?.mint_quote,
+            constraint = token_quote_treasury.owner == treasury.key(),
         )]
-        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        pub token_quote_treasury: Box<Account<'info, TokenAccount>>,
         pub token_program: Program<'info, Token>,
+        pub system_program: Program<'info, System>,
     }
 
-    pub fn bond_withdraw(ctx: Context<BondWithdraw>, index: u64) -> ProgramResult {
+    // buys payout tokens vesting over the market's vesting period, priced
+    // like bond_deposit. `key` is any pubkey the position pda is derived from
+    pub fn market_deposit(
+        ctx: Context<MarketDeposit>,
+        bump: u8,
+        _key: Pubkey,
+        amount: u64,
+        max_price: u64,
+    ) -> ProgramResult {
         let now = unix_now()?;
-        let key: Pubkey;
-        let bump: u8;
-        let amount: u64;
+        let fee: u64;
         {
-            let treasury = &mut ctx.accounts.treasury.load()?;
-            let user = &mut ctx.accounts.user.load_mut()?;
-            key = treasury.key;
-            bump = treasury.bump;
+            let market = &mut ctx.accounts.market.load_mut()?;
+            let position = &mut ctx.accounts.position.load_init()?;
+            require!(market.max_payout != 0, ErrorCode::BondNotConfigured);
 
-            require!(index < user.bonds.len() as u64, ErrorCode::Overflow);
+            let payout_unit = 10_u64.pow(market.payout_decimals as u32);
+            let supply = muldiv(ctx.accounts.mint_payout.supply, ONE, payout_unit)?;
+            let quote = bond_quote(&market.terms(), supply, amount, now)?;
+            market.total_debt = quote.total_debt;
+            market.total_debt_last = now;
             require!(
-                user.bonds[index as usize].bond != Pubkey::default(),
-                ErrorCode::Unknown
+                market.total_debt < market.max_debt,
+                ErrorCode::BondAtMaxDebt
             );
-            let mut user_bond = &mut user.bonds[index as usize];
-            let vesting_progress = muldiv(
-                now.saturating_sub(user_bond.vesting_start),
-                ONE,
-                user_bond.vesting_period,
-            )?
-            .min(ONE);
-            let vested = muldiv(user_bond.staked, vesting_progress, ONE)?;
-            amount = vested.saturating_sub(user_bond.claimed);
-            user_bond.claimed += amount;
-            let bond_key = user_bond.bond.clone();
-            let done = user_bond.claimed == user_bond.staked;
-            if done {
-                user.bonds[index as usize] = UserBond::default();
-            }
-
-            emit!(EventBondWithdraw {
-                signer: ctx.accounts.signer.key(),
-                treasury: ctx.accounts.treasury.key(),
-                bond: bond_key,
-                user: ctx.accounts.user.key(),
-                done,
-                amount,
-            });
+            require!(quote.payout > ONE / 100, ErrorCode::BondPayoutTooSmall);
+            require!(
+                quote.payout <= quote.max_payout,
+                ErrorCode::BondPayoutTooBig
+            );
+            require!(quote.price <= max_price, ErrorCode::BondPriceSlipped);
+            let payout = muldiv(quote.payout, payout_unit, ONE)?;
+            require!(
+                payout <= ctx.accounts.token_payout.amount.saturating_sub(market.owed),
+                ErrorCode::MarketCapacityExceeded
+            );
+
+            market.total_debt += quote.value;
+            market.total_debt_alltime += quote.value;
+            market.owed += payout;
+            position.bump = bump;
+            position.market = ctx.accounts.market.key();
+            position.signer = ctx.accounts.signer.key();
+            position.price = quote.price;
+            position.payout = payout;
+            position.vesting_start = now;
+            position.vesting_period = market.vesting_period;
+            fee = muldiv(amount, ctx.accounts.market_config.load()?.fee, ONE)?;
         }
 
-        if amount > 0 {
+        if fee > 0 {
             token::transfer(
-                CpiContext::new_with_signer(
+                CpiContext::new(
                     ctx.accounts.token_program.to_account_info(),
                     token::Transfer {
-                        from: ctx.accounts.token_staking_vesting.to_account_info(),
-                        to: ctx.accounts.token_staking_user.to_account_info(),
-                        authority: ctx.accounts.treasury.to_account_info(),
+                        from: ctx.accounts.token_quote_user.to_account_info(),
+                        to: ctx.accounts.token_quote_treasury.to_account_info(),
+                        authority: ctx.accounts.signer.to_account_info(),
                     },
-                    &[&[b"treasury", key.as_ref(), &[bump]]],
                 ),
-                amount,
+                fee,
             )?;
         }
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_quote_user.to_account_info(),
+                    to: ctx.accounts.token_quote_issuer.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            amount - fee,
+        )?;
 
         Ok(())
     }
 
     #[derive(Accounts)]
-    pub struct StakingDeposit<'info> {
+    pub struct MarketClaim<'info> {
+        #[account(mut)]
         pub signer: Signer<'info>,
         #[account(mut)]
-        pub treasury: AccountLoader<'info, Treasury>,
-        #[account(mut, constraint = mint_reserve.key() == treasury.load()?.mint_reserve)]
-        pub mint_reserve: Box<Account<'info, Mint>>,
-        #[account(mut, constraint = mint_staking.key() == treasury.load()?.mint_staking)]
-        pub mint_staking: Box<Account<'info, Mint>>,
-        #[account(
-            mut,
-            constraint = token_reserve_user.mint == mint_reserve.key(),
-            constraint = token_reserve_user.owner == signer.key(),
-        )]
-        pub token_reserve_user: Box<Account<'info, TokenAccount>>,
-        #[account(
-            mut,
-            constraint = token_reserve_staking.mint == mint_reserve.key(),
-            constraint = token_reserve_staking.owner == treasury.key(),
-            constraint = token_reserve_staking.key() == treasury.load()?.token_reserve_staking,
-        )]
-        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+        pub market: AccountLoader<'info, Market>,
+        #[account(mut, has_one = market, has_one = signer)]
+        pub position: AccountLoader<'info, MarketPosition>,
+        #[account(mut, constraint = token_payout.key() == market.load()?.token_payout)]
+        pub token_payout: Box<Account<'info, TokenAccount>>,
         #[account(
             mut,
-            constraint = token_staking_user.mint == mint_staking.key(),
-            constraint = token_staking_user.owner == signer.key(),
+            constraint = token_payout_user.mint == token_payout.mint,
+            constraint = token_payout_user.owner == signer.key(),
         )]
-        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        pub token_payout_user: Box<Account<'info, TokenAccount>>,
         pub token_program: Program<'info, Token>,
     }
 
-    pub fn staking_deposit(ctx: Context<StakingDeposit>, amount: u64) -> ProgramResult {
-        let now = unix_now()?;
+    // claims the vested part of a position's payout, closing the position
+    // once fully claimed
+    pub fn market_claim(ctx: Context<MarketClaim>) -> ProgramResult {
+        let treasury: Pubkey;
         let key: Pubkey;
         let bump: u8;
-        let rebase_amount: u64;
-        let value: u64;
+        let amount: u64;
+        let done: bool;
         {
-            let treasury = &mut ctx.accounts.treasury.load_mut()?;
-            key = treasury.key;
-            bump = treasury.bump;
-            require!(amount > 0, ErrorCode::InvalidParameter);
-            let time_elapsed = now.saturating_sub(treasury.staking_last);
-            let rate = muldiv(treasury.staking_rate, time_elapsed, 24 * 60 * 60)?;
-            rebase_amount = muldiv(ctx.accounts.mint_reserve.supply, rate, ONE)?;
-            treasury.staking_last = now;
-        }
-
-        if rebase_amount > 0 {
-            token::mint_to(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    token::MintTo {
-                        mint: ctx.accounts.mint_reserve.to_account_info(),
-                        to: ctx.accounts.token_reserve_staking.to_account_info(),
-                        authority: ctx.accounts.treasury.to_account_info(),
-                    },
-                    &[&[b"treasury", key.as_ref(), &[bump]]],
-                ),
-                amount,
+            let market = &mut ctx.accounts.market.load_mut()?;
+            let position = &mut ctx.accounts.position.load_mut()?;
+            let vested = vested(
+                position.payout,
+                position.vesting_start,
+                position.vesting_period,
+                unix_now()?,
             )?;
-            ctx.accounts.token_reserve_staking.reload()?;
-        }
-
-        {
-            value = if ctx.accounts.mint_staking.supply > 0 {
-                muldiv(
-                    amount,
-                    ctx.accounts.mint_staking.supply,
-                    ctx.accounts.token_reserve_staking.amount,
-                )?
-            } else {
-                amount
-            };
+            amount = vested - position.claimed;
+            require!(amount > 0, ErrorCode::NothingToClaim);
+            position.claimed += amount;
+            market.owed -= amount;
+            done = position.claimed == position.payout;
+            treasury = market.treasury;
+            key = market.key;
+            bump = market.bump;
         }
 
         token::transfer(
-            CpiContext::new(
+            CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
                 token::Transfer {
-                    from: ctx.accounts.token_reserve_user.to_account_info(),
-                    to: ctx.accounts.token_reserve_staking.to_account_info(),
-                    authority: ctx.accounts.signer.to_account_info(),
+                    from: ctx.accounts.token_payout.to_account_info(),
+                    to: ctx.accounts.token_payout_user.to_account_info(),
+                    authority: ctx.accounts.market.to_account_info(),
                 },
+                &[&[b"market", treasury.as_ref(), key.as_ref(), &[bump]]],
             ),
             amount,
         )?;
-        token::mint_to(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                token::MintTo {
-                    mint: ctx.accounts.mint_staking.to_account_info(),
-                    to: ctx.accounts.token_staking_user.to_account_info(),
-                    authority: ctx.accounts.treasury.to_account_info(),
-                },
-                &[&[b"treasury", key.as_ref(), &[bump]]],
-            ),
-            value,
-        )?;
 
-        emit!(EventStakingDeposit {
-            signer: ctx.accounts.signer.key(),
-            treasury: ctx.accounts.treasury.key(),
-            staked: value,
-            amount,
-        });
+        if done {
+            ctx.accounts
+                .position
+                .close(ctx.accounts.signer.to_account_info())?;
+        }
 
         Ok(())
     }
-
     #[derive(Accounts)]
-    pub struct StakingWithdraw<'info> {
-        pub signer: Signer<'info>,
-        #[account(mut)]
-        pub treasury: AccountLoader<'info, Treasury>,
-        #[account(mut, constraint = mint_reserve.key() == treasury.load()?.mint_reserve)]
-        pub mint_reserve: Box<Account<'info, Mint>>,
-        #[account(mut, constraint = mint_staking.key() == treasury.load()?.mint_staking)]
-        pub mint_staking: Box<Account<'info, Mint>>,
-        #[account(
-            mut,
-            constraint = token_reserve_user.mint == mint_reserve.key(),
-            constraint = token_reserve_user.owner == signer.key(),
-        )]
-        pub token_reserve_user: Box<Account<'info, TokenAccount>>,
-        #[account(
-            mut,
-            constraint = token_reserve_staking.mint == mint_reserve.key(),
-            constraint = token_reserve_staking.owner == treasury.key(),
-            constraint = token_reserve_staking.key() == treasury.load()?.token_reserve_staking,
-        )]
-        pub token_reserve_staking: Box<Account<'info, TokenAccount>>,
+    #[instruction(bump: u8)]
+    pub struct FeeSplitInitialize<'info> {
         #[account(
             mut,
-            constraint = token_staking_user.mint == mint_staking.key(),
-            constraint = token_staking_user.owner == signer.key(),
+            constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized,
         )]
-        pub token_staking_user: Box<Account<'info, TokenAccount>>,
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
         #[account(
-            mut,
-            constraint = token_staking_vesting.mint == mint_staking.key(),
-            constraint = token_staking_vesting.owner == treasury.key(),
-            constraint = token_staking_vesting.key() == treasury.load()?.token_staking_vesting,
+            init,
+            payer = signer,
+            seeds = [b"fee_split", treasury.key().as_ref()],
+            bump = bump,
+            space = 8 + size_of::<FeeSplit>(),
         )]
-        pub token_staking_vesting: Box<Account<'info, TokenAccount>>,
+        pub fee_split: AccountLoader<'info, FeeSplit>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn fee_split_initialize(ctx: Context<FeeSplitInitialize>, bump: u8) -> ProgramResult {
+        let fee_split = &mut ctx.accounts.fee_split.load_init()?;
+        fee_split.bump = bump;
+        fee_split.treasury = ctx.accounts.treasury.key();
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct FeeSplitConfigure<'info> {
+        #[account(constraint = signer.key() == treasury.load_versioned()?.authority @ ErrorCode::Unauthorized)]
+        pub signer: Signer<'info>,
+        pub treasury: AccountLoader<'info, Treasury>,
+        #[account(mut, has_one = treasury)]
+        pub fee_split: AccountLoader<'info, FeeSplit>,
+    }
+
+    // replaces all recipients, weights in basis points must add up to 100%
+    pub fn fee_split_configure(
+        ctx: Context<FeeSplitConfigure>,
+        owners: Vec<Pubkey>,
+        weights: Vec<u64>,
+    ) -> ProgramResult {
+        require!(
+            !owners.is_empty()
+                && owners.len() <= FEE_SPLIT_MAX_RECIPIENTS
+                && owners.len() == weights.len(),
+            ErrorCode::InvalidParameter
+        );
+        require!(
+            weights.iter().all(|w| *w > 0) && weights.iter().sum::<u64>() == FEE_SPLIT_BPS,
+            ErrorCode::InvalidParameter
+        );
+        let fee_split = &mut ctx.accounts.fee_split.load_mut()?;
+        fee_split.recipients = Default::default();
+        for i in 0..owners.len() {
+            fee_split.recipients[i].owner = owners[i];
+            fee_split.recipients[i].weight = weights[i];
+        }
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct FeeSplitDistribute<'info> {
+        pub fee_split: AccountLoader<'info, FeeSplit>,
+        #[account(mut, constraint = token_vault.owner == fee_split.key())]
+        pub token_vault: Box<Account<'info, TokenAccount>>,
         pub token_program: Program<'info, Token>,
     }
 
-    pub fn staking_withdraw(ctx: Context<StakingWithdraw>, amount: u64) -> ProgramResult {
-        let now = unix_now()?;
-        let key: Pubkey;
+    // pays out everything accrued in one of the fee split's token accounts.
+    // recipients' token accounts for the same mint are passed as remaining
+    // accounts, in recipient order. rounding dust stays for the next run
+    pub fn fee_split_distribute<'info>(
+        ctx: Context<'_, '_, '_, 'info, FeeSplitDistribute<'info>>,
+    ) -> ProgramResult {
+        let treasury: Pubkey;
         let bump: u8;
-        let rebase_amount: u64;
-        let value: u64;
+        let recipients: [FeeSplitRecipient; FEE_SPLIT_MAX_RECIPIENTS];
         {
-            let treasury = &mut ctx.accounts.treasury.load_mut()?;
-            key = treasury.key;
-            bump = treasury.bump;
-            let time_elapsed = now.saturating_sub(treasury.staking_last);
-            let rate = muldiv(treasury.staking_rate, time_elapsed, 24 * 60 * 60)?;
-            rebase_amount = muldiv(ctx.accounts.mint_reserve.supply, rate, ONE)?;
-            treasury.staking_last = now;
+            let fee_split = ctx.accounts.fee_split.load()?;
+            treasury = fee_split.treasury;
+            bump = fee_split.bump;
+            recipients = fee_split.recipients;
         }
+        let mint = ctx.accounts.token_vault.mint;
+        let total = ctx.accounts.token_vault.amount;
+        require!(total > 0, ErrorCode::NothingToClaim);
 
-        if rebase_amount > 0 {
-            token::mint_to(
+        for (index, recipient) in recipients.iter().filter(|r| r.weight > 0).enumerate() {
+            require!(
+                index < ctx.remaining_accounts.len(),
+                ErrorCode::InvalidParameter
+            );
+            let token = Account::<TokenAccount>::try_from(&ctx.remaining_accounts[index])?;
+            require!(
+                token.owner == recipient.owner && token.mint == mint,
+                ErrorCode::InvalidParameter
+            );
+            let amount = muldiv(total, recipient.weight, FEE_SPLIT_BPS)?;
+            if amount == 0 {
+                continue;
+            }
+
+            token::transfer(
                 CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info(),
-                    token::MintTo {
-                        mint: ctx.accounts.mint_reserve.to_account_info(),
-                        to: ctx.accounts.token_reserve_staking.to_account_info(),
-                        authority: ctx.accounts.treasury.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.token_vault.to_account_info(),
+                        to: token.to_account_info(),
+                        authority: ctx.accounts.fee_split.to_account_info(),
                     },
-                    &[&[b"treasury", key.as_ref(), &[bump]]],
+                    &[&[b"fee_split", treasury.as_ref(), &[bump]]],
                 ),
                 amount,
             )?;
-            ctx.accounts.token_reserve_staking.reload()?;
-        }
 
-        {
-            value = muldiv(
+            emit!(EventFeeDistribute {
+                treasury,
+                recipient: recipient.owner,
+                mint,
                 amount,
-                ctx.accounts.token_reserve_staking.amount,
-                ctx.accounts.mint_staking.supply,
-            )?;
+            });
         }
 
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.token_staking_user.to_account_info(),
-                    to: ctx.accounts.token_staking_vesting.to_account_info(),
-                    authority: ctx.accounts.signer.to_account_info(),
-                },
-            ),
-            amount,
-        )?;
-        token::burn(
+        Ok(())
+    }
+}
+
+fn unix_now() -> Result<u64> {
+    Ok(Clock::get()?.unix_timestamp as u64)
+}
+
+// ends the current epoch once its end time has passed: distributes the
+// amount scheduled for it (part of it as boost to locks), records the
+// staking index and schedules the next epoch's distribution from the
+// (adjusted) staking rate
+#[allow(clippy::too_many_arguments)]
+fn staking_rebase_mint<'info>(
+    treasury: &AccountLoader<'info, Treasury>,
+    epoch: &AccountLoader<'info, Epoch>,
+    lock_curve: &AccountInfo<'info>,
+    mint_reserve: &Account<'info, Mint>,
+    mint_staking: &mut Account<'info, Mint>,
+    token_reserve_staking: &mut Account<'info, TokenAccount>,
+    token_staking_vesting: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+) -> ProgramResult {
+    let now = unix_now()?;
+    let curve = if *lock_curve.owner == ID {
+        Some(AccountLoader::<LockCurve>::try_from(lock_curve)?)
+    } else {
+        None
+    };
+    let key: Pubkey;
+    let bump: u8;
+    let amount: u64;
+    let boost: u64;
+    let mut lock_weight_total = 0;
+    {
+        let treasury = treasury.load_versioned()?;
+        let epoch = epoch.load()?;
+        if now < epoch.end {
+            return Ok(());
+        }
+        key = treasury.key;
+        bump = treasury.bump;
+        amount = epoch.distribute;
+        // locks share the boost by their weight as of the rebase, the
+        // curve keeps it current without them being touched
+        if let Some(curve) = &curve {
+            let curve = &mut curve.load_mut()?;
+            curve.checkpoint(now);
+            lock_weight_total = curve.weight(now);
+        }
+        boost = if lock_weight_total > 0 {
+            muldiv(amount, treasury.lock_boost_rate, ONE)?
+        } else {
+            0
+        };
+    }
+
+    if amount > 0 {
+        token::mint_to(
             CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                token::Burn {
-                    mint: ctx.accounts.mint_staking.to_account_info(),
-                    to: ctx.accounts.token_staking_vesting.to_account_info(),
-                    authority: ctx.accounts.treasury.to_account_info(),
+                token_program.to_account_info(),
+                token::MintTo {
+                    mint: mint_reserve.to_account_info(),
+                    to: token_reserve_staking.to_account_info(),
+                    authority: treasury.to_account_info(),
                 },
                 &[&[b"treasury", key.as_ref(), &[bump]]],
             ),
             amount,
         )?;
-        token::transfer(
+        token_reserve_staking.reload()?;
+    }
+
+    // the rest of the distribution raised the index for all stakers, the
+    // boost is minted as staking tokens at that index and held for locks
+    let mut boost_staked = 0;
+    if boost > 0 {
+        let pool = treasury
+            .load_versioned()?
+            .staking_pool(token_reserve_staking.amount);
+        boost_staked = staking_shares(
+            boost,
+            mint_staking.supply,
+            pool.checked_sub(boost).ok_or(ErrorCode::Overflow)?,
+        )?;
+        token::mint_to(
             CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.token_reserve_staking.to_account_info(),
-                    to: ctx.accounts.token_reserve_user.to_account_info(),
-                    authority: ctx.accounts.treasury.to_account_info(),
+                token_program.to_account_info(),
+                token::MintTo {
+                    mint: mint_staking.to_account_info(),
+                    to: token_staking_vesting.to_account_info(),
+                    authority: treasury.to_account_info(),
                 },
                 &[&[b"treasury", key.as_ref(), &[bump]]],
             ),
-            value,
+            boost_staked,
         )?;
+        mint_staking.reload()?;
+    }
 
-        emit!(EventStakingWithdraw {
-            signer: ctx.accounts.signer.key(),
-            treasury: ctx.accounts.treasury.key(),
-            staked: amount,
-            amount: value,
-        });
+    let treasury_key = treasury.key();
+    let treasury = &mut treasury.load_mut_versioned()?;
+    let epoch = &mut epoch.load_mut()?;
+    let number = epoch.number;
+    if boost_staked > 0 {
+        if let Some(curve) = &curve {
+            curve
+                .load_mut()?
+                .reward(boost_staked, lock_weight_total, now);
+        }
+    }
+    let pool = treasury.staking_pool(token_reserve_staking.amount);
+    let index = staking_index(pool, mint_staking.supply)?;
+    epoch.record(now, index);
+    epoch.number += 1;
+    epoch.gauge_votes_last = epoch.gauge_votes;
+    epoch.gauge_votes = 0;
+    epoch.end += epoch.length;
+    if epoch.adjustment_rate > 0 {
+        treasury.staking_rate = staking_rate_adjust(
+            treasury.staking_rate,
+            epoch.adjustment_rate,
+            epoch.adjustment_target,
+        );
+    }
+    epoch.distribute = epoch_distribute(mint_reserve.supply + amount, treasury.staking_rate)?;
+    treasury.staking_last = now;
 
-        Ok(())
+    emit!(EventStakingRebase {
+        treasury: treasury_key,
+        epoch: number,
+        amount,
+        pool,
+        index,
+        boost,
+    });
+
+    Ok(())
+}
+
+fn treasury_apply(treasury: &mut Treasury, params: &TreasuryParams) -> ProgramResult {
+    require!(params.lock_boost_rate <= ONE, ErrorCode::InvalidParameter);
+    treasury.dao = params.dao;
+    treasury.authority = params.authority;
+    treasury.staking_rate = params.staking_rate;
+    treasury.staking_warmup = params.staking_warmup;
+    treasury.staking_cooldown = params.staking_cooldown;
+    treasury.lock_max_duration = params.lock_max_duration;
+    treasury.lock_boost_rate = params.lock_boost_rate;
+    Ok(())
+}
+
+fn governance_apply(treasury: &mut Treasury, params: &GovernanceParams) -> ProgramResult {
+    require!(params.threshold <= ONE, ErrorCode::InvalidParameter);
+    treasury.governance_quorum = params.quorum;
+    treasury.governance_threshold = params.threshold;
+    Ok(())
+}
+
+fn epoch_apply(epoch: &mut Epoch, params: &EpochParams) -> ProgramResult {
+    require!(params.length > 0, ErrorCode::InvalidParameter);
+    epoch.length = params.length;
+    epoch.adjustment_rate = params.adjustment_rate;
+    epoch.adjustment_target = params.adjustment_target;
+    epoch.gauge_budget = params.gauge_budget;
+    Ok(())
+}
+
+fn bond_apply(bond: &mut Bond, params: &BondParams) -> ProgramResult {
+    require!(params.vesting_period >= 3600, ErrorCode::InvalidParameter);
+    bond.vesting_period = params.vesting_period;
+    bond.rfv_rate = params.rfv_rate;
+    bond.min_price = params.min_price;
+    bond.max_payout = params.max_payout;
+    bond.max_debt = params.max_debt;
+    bond.fee = params.fee;
+    if bond.bcv > 0 {
+        bond.bcv = params.bcv;
+    }
+    Ok(())
+}
+
+fn schedule_apply(
+    schedule: &mut BondSchedule,
+    bond: &Bond,
+    kind: u64,
+    cliff: u64,
+) -> ProgramResult {
+    require!(kind <= VESTING_EXPONENTIAL, ErrorCode::InvalidParameter);
+    require!(
+        kind != VESTING_CLIFF || (cliff > 0 && cliff < bond.vesting_period),
+        ErrorCode::InvalidParameter
+    );
+    schedule.kind = kind;
+    schedule.cliff = if kind == VESTING_CLIFF { cliff } else { 0 };
+    Ok(())
+}
+
+// an account's data is accepted by migrate_* when it's older than the
+// current layout, never newer
+fn migration_check(version: u8, current: u8) -> ProgramResult {
+    require!(version < current, ErrorCode::AccountVersion);
+    Ok(())
+}
+
+// an account of ours of type T in any layout up to the current one
+fn migration_read<T: Versioned>(info: &AccountInfo, program_id: &Pubkey) -> Result<T> {
+    require!(info.owner == program_id, ErrorCode::InvalidParameter);
+    let data = info.try_borrow_data()?;
+    require!(
+        data.len() >= 8 && data[..8] == T::discriminator(),
+        ErrorCode::InvalidParameter
+    );
+    migration_upgrade(&data[8..])
+}
+
+// layouts only ever append fields, so older data is a prefix of T with
+// what's missing zeroed (version included), any version specific
+// conversion goes here
+pub fn migration_upgrade<T: Versioned>(data: &[u8]) -> Result<T> {
+    let mut account: T = bytemuck::Zeroable::zeroed();
+    let bytes = bytemuck::bytes_of_mut(&mut account);
+    require!(data.len() <= bytes.len(), ErrorCode::AccountVersion);
+    bytes[..data.len()].copy_from_slice(data);
+    migration_check(account.version(), T::VERSION)?;
+    Ok(account)
+}
+
+// copies an account's data to `migration` and closes it like anchor's
+// `close`, refunding its rent to `destination`
+fn migration_begin<'info>(
+    migration: &mut Migration,
+    bump: u8,
+    info: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+) -> ProgramResult {
+    migration.bump = bump;
+    migration.account = info.key();
+    migration.data = info.try_borrow_data()?[8..].to_vec();
+    **destination.try_borrow_mut_lamports()? += info.lamports();
+    **info.try_borrow_mut_lamports()? = 0;
+    info.try_borrow_mut_data()?.fill(0);
+    Ok(())
+}
+
+// points an oracle at its sources, checking they're what its kind reads
+#[allow(clippy::too_many_arguments)]
+fn oracle_apply(
+    oracle: &mut Oracle,
+    treasury: &Treasury,
+    source: &AccountInfo,
+    source_quote: &AccountInfo,
+    program_id: &Pubkey,
+    kind: u64,
+    quote_decimals: u64,
+    max_age: u64,
+    window: u64,
+    interval: u64,
+) -> ProgramResult {
+    match kind {
+        ORACLE_POOL => {
+            let reserve = Account::<TokenAccount>::try_from(source)?;
+            Account::<TokenAccount>::try_from(source_quote)?;
+            require!(
+                reserve.mint == treasury.mint_reserve
+                    && quote_decimals <= 18
+                    && window > 0
+                    && window <= max_age
+                    && interval > 0
+                    && interval <= window,
+                ErrorCode::InvalidParameter
+            );
+        }
+        ORACLE_FEED => {
+            require!(source.owner == program_id, ErrorCode::InvalidParameter);
+            AccountLoader::<PriceFeed>::try_from(source)?;
+        }
+        _ => return Err(ErrorCode::InvalidParameter.into()),
     }
+    oracle.kind = kind;
+    oracle.source = source.key();
+    oracle.source_quote = source_quote.key();
+    oracle.quote_decimals = quote_decimals;
+    oracle.max_age = max_age;
+    oracle.window = window;
+    oracle.interval = interval;
+    oracle.price = 0;
+    oracle.updated = 0;
+    oracle.spot = 0;
+    oracle.spot_updated = 0;
+    oracle.price_cumulative = 0;
+    oracle.window_start = 0;
+    oracle.window_cumulative = 0;
+    Ok(())
 }
 
-fn muldiv(a: u64, m: u64, d: u64) -> Result<u64> {
-    msg!("muldiv a {} m {} d {}", a, m, d);
-    let result = a as u128 * m as u128 / d as u128;
-    if result > u64::MAX as u128 {
-        return Err(ErrorCode::Overflow.into());
+// appends an observation, or updates the latest one when it's less than
+// BOND_OBSERVATION_INTERVAL old, its price then holding from its time
+fn observation_record(observations: &mut BondObservations, now: u64, price: u64, debt_ratio: u64) {
+    let latest = observations.observations[observations.index as usize];
+    if observations.count > 0 && now.saturating_sub(latest.time) < BOND_OBSERVATION_INTERVAL {
+        let observation = &mut observations.observations[observations.index as usize];
+        observation.price = price;
+        observation.debt_ratio = debt_ratio;
+        return;
+    }
+    let mut price_cumulative = 0;
+    if observations.count > 0 {
+        observations.index = (observations.index + 1) % BOND_OBSERVATIONS as u64;
+        price_cumulative =
+            latest.price_cumulative + latest.price as u128 * (now - latest.time) as u128;
     }
-    Ok(result as u64)
+    observations.count = (observations.count + 1).min(BOND_OBSERVATIONS as u64);
+    observations.observations[observations.index as usize] = BondObservation {
+        time: now,
+        price,
+        debt_ratio,
+        price_cumulative,
+    };
 }
 
-fn unix_now() -> Result<u64> {
-    Ok(Clock::get()?.unix_timestamp as u64)
+// sorted pair sha256 merkle proof
+fn merkle_verify(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let mut node = leaf;
+    for sibling in proof {
+        node = if node <= *sibling {
+            hashv(&[&node, sibling])
+        } else {
+            hashv(&[sibling, &node])
+        }
+        .to_bytes();
+    }
+    node == root
+}
+
+fn multisig_owners_check(owners: &[Pubkey], threshold: u64) -> ProgramResult {
+    require!(
+        !owners.is_empty() && owners.len() <= MULTISIG_MAX_OWNERS,
+        ErrorCode::InvalidParameter
+    );
+    require!(
+        threshold > 0 && threshold <= owners.len() as u64,
+        ErrorCode::InvalidParameter
+    );
+    for (i, owner) in owners.iter().enumerate() {
+        require!(!owners[..i].contains(owner), ErrorCode::InvalidParameter);
+    }
+    Ok(())
+}
+
+// moves a bond's gauge votes to gauge_votes_last once the epoch they were
+// cast in is over
+fn bond_gauge_roll(bond: &mut Bond, number: u64) {
+    if bond.gauge_epoch == number {
+        return;
+    }
+    bond.gauge_votes_last = if bond.gauge_epoch + 1 == number {
+        bond.gauge_votes
+    } else {
+        0
+    };
+    bond.gauge_votes = 0;
+    bond.gauge_epoch = number;
 }
@@ -0,0 +1,335 @@
+use crate::{
+    Bond, BondObservations, Epoch, ErrorCode, Lock, LockCurve, ProtocolMetrics, Result, Treasury,
+    UserBond, BOND_OBSERVATIONS,
+};
+use anchor_lang::prelude::*;
+
+pub const ONE: u64 = 10_u64.pow(9);
+pub const DAY: u64 = 24 * 60 * 60;
+
+// VestingSchedule.kind
+// linearly over the period
+pub const VESTING_LINEAR: u64 = 0;
+// nothing until the cliff, then linearly from the start
+pub const VESTING_CLIFF: u64 = 1;
+// in tranches every 30 days, the rest at the end of the period
+pub const VESTING_MONTHLY: u64 = 2;
+// doubling every tenth of the period, back-loading most of it
+pub const VESTING_EXPONENTIAL: u64 = 3;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VestingSchedule {
+    pub kind: u64,
+    pub start: u64,
+    pub period: u64,
+    // seconds after start, for VESTING_CLIFF
+    pub cliff: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BondQuote {
+    // total debt after decay
+    pub total_debt: u64,
+    pub debt_ratio: u64,
+    pub price: u64,
+    // rfv of the amount deposited
+    pub value: u64,
+    pub payout: u64,
+    pub fee: u64,
+    pub max_payout: u64,
+}
+
+// rfv of `amount` bond tokens
+pub fn bond_value(bond: &Bond, amount: u64) -> Result<u64> {
+    let amount_scaled = muldiv(amount, ONE, 10_u64.pow(bond.token_decimals as u32))?;
+    muldiv(amount_scaled, bond.rfv_rate, ONE)
+}
+
+// computes what a bond deposit of `amount` (in bond token units) would yield
+// at time `now`, without mutating the bond
+pub fn bond_quote(bond: &Bond, total_reserves: u64, amount: u64, now: u64) -> Result<BondQuote> {
+    let total_debt = debt_decay(
+        bond.total_debt,
+        bond.total_debt_last,
+        now,
+        bond.vesting_period,
+    )?;
+    let debt_ratio = muldiv(total_debt, ONE, total_reserves.max(1))?;
+    let price = (ONE + muldiv(bond.bcv, debt_ratio, ONE)?).max(bond.min_price);
+    let value = bond_value(bond, amount)?;
+    let payout = muldiv(value, ONE, price)?;
+    let fee = muldiv(payout, bond.fee, ONE)?.min(value.saturating_sub(payout));
+    let max_payout = if total_reserves == 0 {
+        1000 * ONE
+    } else {
+        muldiv(total_reserves, bond.max_payout, ONE)?
+    };
+    Ok(BondQuote {
+        total_debt,
+        debt_ratio,
+        price,
+        value,
+        payout,
+        fee,
+        max_payout,
+    })
+}
+
+// time weighted average bond price over the `window` seconds before `now`,
+// from the cumulative prices of the observations around its start
+pub fn observation_twap(observations: &BondObservations, now: u64, window: u64) -> Result<u64> {
+    require!(window > 0 && window <= now, ErrorCode::InvalidParameter);
+    require!(observations.count > 0, ErrorCode::BondTwapWindow);
+    let start = now - window;
+    let n = BOND_OBSERVATIONS as u64;
+    let latest = observations.observations[observations.index as usize];
+    let mut price_cumulative_start = None;
+    for i in 0..observations.count {
+        let o = observations.observations[((observations.index + n - i) % n) as usize];
+        if o.time <= start {
+            price_cumulative_start =
+                Some(o.price_cumulative + o.price as u128 * (start - o.time) as u128);
+            break;
+        }
+    }
+    let price_cumulative_start = price_cumulative_start.ok_or(ErrorCode::BondTwapWindow)?;
+    let price_cumulative =
+        latest.price_cumulative + latest.price as u128 * now.saturating_sub(latest.time) as u128;
+    Ok(((price_cumulative - price_cumulative_start) / window as u128) as u64)
+}
+
+// amount of reserve to distribute to stakers over the next epoch
+pub fn epoch_distribute(supply: u64, staking_rate: u64) -> Result<u64> {
+    muldiv(supply, staking_rate, ONE)
+}
+
+// moves the staking rate by `adjustment_rate` toward `adjustment_target`
+pub fn staking_rate_adjust(staking_rate: u64, adjustment_rate: u64, adjustment_target: u64) -> u64 {
+    if staking_rate < adjustment_target {
+        (staking_rate + adjustment_rate).min(adjustment_target)
+    } else {
+        staking_rate
+            .saturating_sub(adjustment_rate)
+            .max(adjustment_target)
+    }
+}
+
+// reserve tokens per staking token, 1e9
+pub fn staking_index(pool: u64, staking_supply: u64) -> Result<u64> {
+    if staking_supply == 0 {
+        return Ok(ONE);
+    }
+    muldiv(pool, ONE, staking_supply)
+}
+
+// (1 + rate) ^ periods, 1e9, saturating at u64::MAX
+pub fn compound(rate: u64, periods: u64) -> u64 {
+    let one = ONE as u128;
+    let mut result = one;
+    let mut base = one + rate as u128;
+    let mut n = periods;
+    while n > 0 {
+        if n & 1 == 1 {
+            result = (result * base / one).min(u64::MAX as u128);
+        }
+        base = (base * base / one).min(u64::MAX as u128);
+        n >>= 1;
+    }
+    result as u64
+}
+
+// epochs the staking pool compounds at `rate` before going over `reserves`
+fn runway_epochs(pool: u64, reserves: u64, rate: u64) -> u64 {
+    let over = |n| pool as u128 * compound(rate, n) as u128 / ONE as u128 > reserves as u128;
+    if over(0) {
+        return 0;
+    }
+    let mut hi = 1;
+    while !over(hi) {
+        if hi > u64::MAX / 2 {
+            return u64::MAX;
+        }
+        hi *= 2;
+    }
+    // over(hi) and not over(lo)
+    let mut lo = hi / 2;
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if over(mid) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    lo
+}
+
+pub fn protocol_metrics(
+    treasury: &Treasury,
+    epoch: &Epoch,
+    reserve_supply: u64,
+    staking_supply: u64,
+    token_reserve_staking_amount: u64,
+) -> Result<ProtocolMetrics> {
+    let pool = treasury.staking_pool(token_reserve_staking_amount);
+    // nothing staked, nothing to earn yet
+    let reward_rate = if pool == 0 {
+        0
+    } else {
+        muldiv(epoch.distribute, ONE, pool)?
+    };
+    let length = epoch.length.max(1);
+    let runway_days = if reward_rate == 0 {
+        u64::MAX
+    } else {
+        let epochs = runway_epochs(pool, treasury.total_reserves, reward_rate);
+        (epochs as u128 * length as u128 / DAY as u128).min(u64::MAX as u128) as u64
+    };
+    Ok(ProtocolMetrics {
+        backing_per_token: muldiv(treasury.total_reserves, ONE, reserve_supply.max(1))?,
+        staking_index: staking_index(pool, staking_supply)?,
+        reward_rate,
+        roi_five_day: compound(reward_rate, 5 * DAY / length) - ONE,
+        apy: compound(reward_rate, 365 * DAY / length) - ONE,
+        runway_days,
+    })
+}
+
+// staking tokens to mint for `amount` reserve tokens entering the pool
+pub fn staking_shares(amount: u64, staking_supply: u64, pool: u64) -> Result<u64> {
+    if staking_supply > 0 {
+        muldiv(amount, staking_supply, pool)
+    } else {
+        Ok(amount)
+    }
+}
+
+// reserve tokens redeemable for `shares` staking tokens
+pub fn staking_value(shares: u64, staking_supply: u64, pool: u64) -> Result<u64> {
+    muldiv(shares, pool, staking_supply)
+}
+
+// weight of a lock of `slope` (see lock_slope) ending at `end`: the full
+// amount for a lock of the max_duration it was made with, decaying linearly
+// to 0 at the end
+pub fn lock_weight(slope: u128, end: u64, now: u64) -> Result<u64> {
+    let weight = slope
+        .checked_mul(end.saturating_sub(now) as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / ONE as u128;
+    if weight > u64::MAX as u128 {
+        return Err(ErrorCode::Overflow.into());
+    }
+    Ok(weight as u64)
+}
+
+// weight slope of a lock of `amount`, 1e9: its weight at `t` is
+// slope * (end - t) / 1e9, for both votes and boost
+pub fn lock_slope(amount: u64, max_duration: u64) -> u128 {
+    amount as u128 * ONE as u128 / max_duration as u128
+}
+
+// boost earned by a lock of `slope` ending at `end`, from how much the lock
+// curve's reward accumulators grew since its last update
+pub fn lock_earned(slope: u128, end: u64, per_weight: u128, per_weight_time: u128) -> Result<u64> {
+    let one = ONE as u128;
+    // sum over rebases of the reward per weight times the time left to end
+    let left = per_weight
+        .wrapping_mul(end as u128)
+        .wrapping_sub(per_weight_time);
+    let earned = slope
+        .checked_mul(left / one)
+        .and_then(|e| e.checked_add(slope * (left % one) / one))
+        .ok_or(ErrorCode::Overflow)?
+        / one;
+    if earned > u64::MAX as u128 {
+        return Err(ErrorCode::Overflow.into());
+    }
+    Ok(earned as u64)
+}
+
+// accrues the boost earned by a lock since its last update, up to its end,
+// settling it on the lock curve once that's over
+pub fn lock_update(lock: &mut Lock, curve: &mut LockCurve, now: u64) -> Result<()> {
+    curve.checkpoint(now);
+    if lock.settled > 0 {
+        return Ok(());
+    }
+    let ended = curve.ended(lock.end);
+    let (per_weight, per_weight_time) = if ended {
+        curve.accumulators_at(lock.end)
+    } else {
+        (curve.reward_per_weight, curve.reward_per_weight_time)
+    };
+    let earned = lock_earned(
+        lock.slope,
+        lock.end,
+        per_weight.wrapping_sub(lock.reward_per_weight_paid),
+        per_weight_time.wrapping_sub(lock.reward_per_weight_time_paid),
+    )?;
+    lock.pending = lock
+        .pending
+        .checked_add(earned)
+        .ok_or(ErrorCode::Overflow)?;
+    lock.reward_per_weight_paid = per_weight;
+    lock.reward_per_weight_time_paid = per_weight_time;
+    if ended && lock.slope > 0 {
+        curve.settle(lock.end);
+        lock.settled = now;
+    }
+    Ok(())
+}
+
+// part of `amount` vested by `now` following `schedule`
+pub fn vested_amount(schedule: &VestingSchedule, amount: u64, now: u64) -> Result<u64> {
+    let elapsed = now.saturating_sub(schedule.start);
+    if elapsed >= schedule.period {
+        return Ok(amount);
+    }
+    match schedule.kind {
+        VESTING_CLIFF if elapsed < schedule.cliff => Ok(0),
+        VESTING_MONTHLY => {
+            let month = 30 * DAY;
+            muldiv(amount, elapsed / month * month, schedule.period)
+        }
+        VESTING_EXPONENTIAL => {
+            // (2^(10x) - 1) / (2^10 - 1) for progress x, with 2^(i+f)
+            // approximated as 2^i * (1 + f)
+            let x = muldiv(elapsed, 10 * ONE, schedule.period)?;
+            let curve = (1 << (x / ONE)) * (ONE + x % ONE) - ONE;
+            muldiv(amount, curve, 1023 * ONE)
+        }
+        _ => vested(amount, schedule.start, schedule.period, now),
+    }
+}
+
+// staked tokens vested linearly by `now`
+pub fn vested(staked: u64, vesting_start: u64, vesting_period: u64, now: u64) -> Result<u64> {
+    let vesting_progress = muldiv(now.saturating_sub(vesting_start), ONE, vesting_period)?.min(ONE);
+    muldiv(staked, vesting_progress, ONE)
+}
+
+// staking tokens released and burned when exiting `bond` early at `now`,
+// exit_penalty of the part not vested yet being burned
+pub fn exit_early(bond: &UserBond, exit_penalty: u64, now: u64) -> Result<(u64, u64)> {
+    let vested = vested_amount(&bond.schedule(), bond.staked, now)?;
+    let penalty = muldiv(bond.staked - vested, exit_penalty, ONE)?;
+    Ok((bond.staked - bond.claimed - penalty, penalty))
+}
+
+// `debt` recorded at `last` as of `now`, decaying linearly to 0 over
+// `vesting_period`
+pub fn debt_decay(debt: u64, last: u64, now: u64, vesting_period: u64) -> Result<u64> {
+    let decay = muldiv(debt, now.saturating_sub(last), vesting_period)?;
+    Ok(debt.saturating_sub(decay))
+}
+
+pub fn muldiv(a: u64, m: u64, d: u64) -> Result<u64> {
+    #[cfg(feature = "debug-logs")]
+    msg!("muldiv a {} m {} d {}", a, m, d);
+    let result = a as u128 * m as u128 / d as u128;
+    if result > u64::MAX as u128 {
+        return Err(ErrorCode::Overflow.into());
+    }
+    Ok(result as u64)
+}
@@ -0,0 +1,361 @@
+// Compute units used by the busiest instructions. Each one is sent with a
+// compute budget request of its limit so going over it fails the test, and
+// what it used is printed. Runs the built program, `anchor build` then:
+//   BPF_OUT_DIR=target/deploy cargo test -p reserve --test compute_units -- --nocapture
+// and is skipped without it.
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use solana_sdk::{system_instruction, system_program, sysvar};
+use std::path::Path;
+use std::sync::Mutex;
+
+// raise these deliberately, alongside the change that needs it
+const BUDGET_INITIALIZE: u32 = 80_000;
+const BUDGET_USER_INITIALIZE: u32 = 20_000;
+const BUDGET_BOND_DEPOSIT: u32 = 100_000;
+
+// the runtime's "Program <id> consumed <n> of <m> compute units" lines,
+// banks_client doesn't return logs
+struct ConsumedLogs(Mutex<Vec<String>>);
+
+impl log::Log for ConsumedLogs {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let line = record.args().to_string();
+        if line.contains(" consumed ") {
+            self.0.lock().unwrap().push(line);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[tokio::test]
+async fn compute_units() {
+    let program = match std::env::var("BPF_OUT_DIR") {
+        Ok(dir) => Path::new(&dir).join("reserve.so"),
+        Err(_) => {
+            println!("skipping, BPF_OUT_DIR isn't set");
+            return;
+        }
+    };
+    if !program.exists() {
+        println!("skipping, {} isn't built", program.display());
+        return;
+    }
+
+    // installed before ProgramTest sets up its own logger, which then
+    // leaves it in place
+    let logs: &'static ConsumedLogs = Box::leak(Box::new(ConsumedLogs(Mutex::new(vec![]))));
+    log::set_logger(logs).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let mut ctx = ProgramTest::new("reserve", reserve::id(), None)
+        .start_with_context()
+        .await;
+    let payer = ctx.payer.pubkey();
+    let dao = Keypair::new().pubkey();
+
+    let key = Keypair::new().pubkey();
+    let (treasury, bump) = pda(&[b"treasury", key.as_ref()]);
+    let (mint_reserve, mint_reserve_bump) = pda(&[b"treasury_mint_reserve"]);
+    let (mint_staking, mint_staking_bump) = pda(&[b"treasury_mint_staking"]);
+    let (token_reserve_staking, token_reserve_staking_bump) =
+        pda(&[b"treasury_token_reserve_staking"]);
+    let (token_staking_vesting, token_staking_vesting_bump) =
+        pda(&[b"treasury_token_staking_vesting"]);
+    send_budget(
+        &mut ctx,
+        logs,
+        "initialize",
+        BUDGET_INITIALIZE,
+        ix(
+            reserve::accounts::Initialize {
+                signer: payer,
+                treasury,
+                mint_reserve,
+                mint_staking,
+                token_reserve_staking,
+                token_staking_vesting,
+                dao,
+                rent: sysvar::rent::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            },
+            reserve::instruction::Initialize {
+                key,
+                bump,
+                _mint_reserve_bump: mint_reserve_bump,
+                _mint_staking_bump: mint_staking_bump,
+                _token_reserve_staking_bump: token_reserve_staking_bump,
+                _token_staking_vesting_bump: token_staking_vesting_bump,
+            },
+        ),
+    )
+    .await;
+
+    let (role, role_bump) = pda(&[b"role", treasury.as_ref(), payer.as_ref()]);
+    send(
+        &mut ctx,
+        vec![
+            ix(
+                reserve::accounts::RoleInitialize {
+                    signer: payer,
+                    treasury,
+                    member: payer,
+                    role,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::RoleInitialize { bump: role_bump },
+            ),
+            ix(
+                reserve::accounts::RoleConfigure {
+                    signer: payer,
+                    treasury,
+                    role,
+                },
+                reserve::instruction::RoleGrant {
+                    roles: reserve::ROLE_BOND_MANAGER,
+                },
+            ),
+        ],
+        &[],
+    )
+    .await;
+
+    let mint_bond = Keypair::new();
+    create_mint(&mut ctx, &mint_bond, 6).await;
+    let token_bond_user = create_token(&mut ctx, &mint_bond.pubkey(), &payer).await;
+    let token_bond_treasury = create_token(&mut ctx, &mint_bond.pubkey(), &treasury).await;
+    let token_reserve_dao = create_token(&mut ctx, &mint_reserve, &dao).await;
+    send(
+        &mut ctx,
+        vec![spl_token::instruction::mint_to(
+            &spl_token::ID,
+            &mint_bond.pubkey(),
+            &token_bond_user,
+            &payer,
+            &[],
+            1_000_000_000,
+        )
+        .unwrap()],
+        &[],
+    )
+    .await;
+
+    let (bond, bond_bump) = pda(&[b"bond", treasury.as_ref(), mint_bond.pubkey().as_ref()]);
+    send(
+        &mut ctx,
+        vec![
+            ix(
+                reserve::accounts::BondInitialize {
+                    signer: payer,
+                    treasury,
+                    role,
+                    bond,
+                    mint_bond: mint_bond.pubkey(),
+                    migration: pda(&[b"migration", bond.as_ref()]).0,
+                    system_program: system_program::ID,
+                },
+                reserve::instruction::BondInitialize { _bump: bond_bump },
+            ),
+            ix(
+                reserve::accounts::BondConfigure {
+                    signer: payer,
+                    treasury,
+                    role,
+                    bond,
+                },
+                reserve::instruction::BondConfigure {
+                    vesting_period: 3600,
+                    rfv_rate: reserve::math::ONE,
+                    min_price: reserve::math::ONE,
+                    max_payout: 10 * reserve::math::ONE,
+                    max_debt: 100_000 * reserve::math::ONE,
+                    fee: 5000,
+                    bcv: 500,
+                },
+            ),
+        ],
+        &[],
+    )
+    .await;
+
+    let (user, user_bump) = pda(&[b"user", treasury.as_ref(), payer.as_ref()]);
+    send_budget(
+        &mut ctx,
+        logs,
+        "user_initialize",
+        BUDGET_USER_INITIALIZE,
+        ix(
+            reserve::accounts::UserInitialize {
+                signer: payer,
+                treasury,
+                user,
+                system_program: system_program::ID,
+            },
+            reserve::instruction::UserInitialize { bump: user_bump },
+        ),
+    )
+    .await;
+
+    send_budget(
+        &mut ctx,
+        logs,
+        "bond_deposit",
+        BUDGET_BOND_DEPOSIT,
+        ix(
+            reserve::accounts::BondDeposit {
+                signer: payer,
+                treasury,
+                bond,
+                allowlist: pda(&[b"bond_allowlist", bond.as_ref()]).0,
+                allowance: pda(&[b"bond_allowance", bond.as_ref(), payer.as_ref()]).0,
+                schedule: pda(&[b"bond_schedule", bond.as_ref()]).0,
+                observations: pda(&[b"bond_observations", bond.as_ref()]).0,
+                oracle: pda(&[b"oracle", treasury.as_ref()]).0,
+                user,
+                mint_bond: mint_bond.pubkey(),
+                mint_reserve,
+                mint_staking,
+                token_bond_user,
+                token_bond_treasury,
+                token_reserve_dao,
+                token_reserve_staking,
+                token_staking_vesting,
+                token_program: spl_token::ID,
+            },
+            reserve::instruction::BondDeposit {
+                amount: 300_000_000,
+                max_price: u64::MAX,
+                max_amount: 0,
+                proof: vec![],
+            },
+        ),
+    )
+    .await;
+}
+
+fn pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &reserve::id())
+}
+
+fn ix(accounts: impl ToAccountMetas, args: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: reserve::id(),
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+async fn send(ctx: &mut ProgramTestContext, ixs: Vec<Instruction>, signers: &[&Keypair]) {
+    let mut all = vec![&ctx.payer];
+    all.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(
+        &ixs,
+        Some(&ctx.payer.pubkey()),
+        &all,
+        ctx.last_blockhash,
+    );
+    ctx.banks_client.process_transaction(tx).await.unwrap();
+}
+
+// fails when `ix` uses more than `budget` compute units, prints what it used
+async fn send_budget(
+    ctx: &mut ProgramTestContext,
+    logs: &ConsumedLogs,
+    name: &str,
+    budget: u32,
+    ix: Instruction,
+) {
+    let tx = Transaction::new_signed_with_payer(
+        &[ComputeBudgetInstruction::request_units(budget), ix],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+    logs.0.lock().unwrap().clear();
+    if let Err(e) = ctx.banks_client.process_transaction(tx).await {
+        panic!(
+            "instruction {} failed within {} compute units: {:?}",
+            name, budget, e
+        );
+    }
+    let prefix = format!("Program {} consumed ", reserve::id());
+    let consumed = logs.0.lock().unwrap().iter().rev().find_map(|line| {
+        line.strip_prefix(&prefix)?
+            .split(' ')
+            .next()?
+            .parse::<u64>()
+            .ok()
+    });
+    match consumed {
+        Some(units) => println!("{} used {} of {} compute units", name, units, budget),
+        None => println!("{} used an unknown number of compute units", name),
+    }
+}
+
+async fn create_mint(ctx: &mut ProgramTestContext, mint: &Keypair, decimals: u8) {
+    let payer = ctx.payer.pubkey();
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    send(
+        ctx,
+        vec![
+            system_instruction::create_account(
+                &payer,
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::ID,
+                &mint.pubkey(),
+                &payer,
+                None,
+                decimals,
+            )
+            .unwrap(),
+        ],
+        &[mint],
+    )
+    .await;
+}
+
+async fn create_token(ctx: &mut ProgramTestContext, mint: &Pubkey, owner: &Pubkey) -> Pubkey {
+    let payer = ctx.payer.pubkey();
+    let token = Keypair::new();
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    send(
+        ctx,
+        vec![
+            system_instruction::create_account(
+                &payer,
+                &token.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::ID,
+                &token.pubkey(),
+                mint,
+                owner,
+            )
+            .unwrap(),
+        ],
+        &[&token],
+    )
+    .await;
+    token.pubkey()
+}
@@ -0,0 +1,85 @@
+// Lock weights, and boost accounting of the lock curve across the reuse of
+// its period entries, which happens LOCK_CURVE_PERIODS periods apart.
+
+use anchor_lang::prelude::ProgramError;
+use reserve::math::{lock_slope, lock_update};
+use reserve::{ErrorCode, Lock, LockCurve, LOCK_CURVE_HORIZON, LOCK_CURVE_PERIODS};
+
+const PERIOD: u64 = 10;
+const MAX_DURATION: u64 = 100;
+
+fn curve(now: u64) -> LockCurve {
+    let mut curve: LockCurve = bytemuck::Zeroable::zeroed();
+    curve.period = PERIOD;
+    curve.next = now / PERIOD + 1;
+    curve
+}
+
+// what lock_deposit does to a new lock
+fn deposit(curve: &mut LockCurve, amount: u64, end: u64, now: u64) -> Result<Lock, ProgramError> {
+    let mut lock: Lock = bytemuck::Zeroable::zeroed();
+    lock_update(&mut lock, curve, now).unwrap();
+    lock.amount = amount;
+    lock.end = end;
+    lock.slope = lock_slope(amount, MAX_DURATION);
+    lock.reward_per_weight_paid = curve.reward_per_weight;
+    lock.reward_per_weight_time_paid = curve.reward_per_weight_time;
+    curve.add(lock.slope, end)?;
+    Ok(lock)
+}
+
+fn rebase(curve: &mut LockCurve, boost: u64, now: u64) {
+    curve.checkpoint(now);
+    let weight = curve.weight(now);
+    curve.reward(boost, weight, now);
+}
+
+#[test]
+fn boost_survives_period_wrap() {
+    let mut curve = curve(0);
+    let mut lock = deposit(&mut curve, 1000, 100, 0).unwrap();
+    rebase(&mut curve, 1000, 50);
+
+    // earliest a new lock can end in the period reusing the entry of the
+    // first one
+    let wrapped = 100 + LOCK_CURVE_PERIODS as u64 * PERIOD;
+    let now = wrapped - LOCK_CURVE_HORIZON * PERIOD;
+    curve.checkpoint(now);
+    assert_eq!(
+        deposit(&mut curve, 1000, wrapped, now).err(),
+        Some(ErrorCode::LockCurvePeriodUnsettled.into())
+    );
+
+    // settling the ended lock frees the entry and keeps its boost
+    lock_update(&mut lock, &mut curve, now).unwrap();
+    assert_eq!({ lock.pending }, 1000);
+    assert_eq!({ lock.settled }, now);
+    deposit(&mut curve, 1000, wrapped, now).unwrap();
+
+    // and the reused entry doesn't pay it again
+    rebase(&mut curve, 1000, now + 1);
+    lock_update(&mut lock, &mut curve, now + 2).unwrap();
+    assert_eq!({ lock.pending }, 1000);
+}
+
+#[test]
+fn extended_lock_moves_period() {
+    let mut curve = curve(0);
+    let lock = deposit(&mut curve, 1000, 100, 0).unwrap();
+    curve.remove(lock.slope, lock.end);
+    curve.add(lock.slope, 200).unwrap();
+    assert_eq!({ curve.periods[10].locks }, 0);
+    assert_eq!({ curve.periods[20].locks }, 1);
+    assert_eq!(curve.weight(0), lock.slope * 200);
+}
+
+#[test]
+fn voting_weight_follows_lock_slope() {
+    let mut curve = curve(0);
+    // made with a max_duration of 100, whatever it is now
+    let lock = deposit(&mut curve, 1000, 100, 0).unwrap();
+    assert_eq!(lock.voting_weight(0).unwrap(), 1000);
+    assert_eq!(lock.voting_weight(50).unwrap(), 500);
+    assert_eq!(lock.voting_weight(100).unwrap(), 0);
+    assert_eq!(lock.voting_weight(150).unwrap(), 0);
+}
@@ -0,0 +1,59 @@
+// Accounts saved in older layouts, upgraded the way migrate_*_finish does.
+
+use anchor_lang::Discriminator;
+use reserve::{migration_upgrade, Bond};
+use solana_sdk::pubkey::Pubkey;
+
+// the data of an account dumped by `solana account --output json`, which
+// is what the test validator loads fixtures from
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!(
+        "{}/../../tests/fixtures/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    base64::decode(json["account"]["data"][0].as_str().unwrap()).unwrap()
+}
+
+#[test]
+fn bond_v0() {
+    let data = fixture("bond_v0.json");
+    assert_eq!(data[..8], Bond::discriminator());
+    assert!(data.len() < 8 + std::mem::size_of::<Bond>());
+
+    let bond: Bond = migration_upgrade(&data[8..]).unwrap();
+    let (treasury, _) = Pubkey::find_program_address(&[b"treasury", &[1; 32]], &reserve::id());
+    assert_eq!({ bond.bump }, 255);
+    assert_eq!({ bond.treasury }, treasury);
+    assert_eq!({ bond.mint_bond }, Pubkey::new_from_array([2; 32]));
+    assert_eq!({ bond.token_decimals }, 6);
+    assert_eq!({ bond.vesting_period }, 3600);
+    assert_eq!({ bond.rfv_rate }, 1_000_000_000);
+    assert_eq!({ bond.min_price }, 1_000_000_000);
+    assert_eq!({ bond.max_payout }, 10_000_000_000);
+    assert_eq!({ bond.max_debt }, 1_000_000_000_000);
+    assert_eq!({ bond.fee }, 5000);
+    assert_eq!({ bond.bcv }, 500);
+    assert_eq!({ bond.total_debt }, 7_000_000_000);
+    assert_eq!({ bond.total_debt_last }, 1_600_000_000);
+    assert_eq!({ bond.total_debt_alltime }, 123_000_000_000);
+    assert_eq!({ bond.gauge_epoch }, 3);
+    assert_eq!({ bond.gauge_votes }, 11_000_000_000);
+    assert_eq!({ bond.gauge_votes_last }, 13_000_000_000);
+    assert_eq!({ bond.paused }, 1_600_000_100);
+    assert_eq!({ bond.max_debt_user }, 50_000_000_000);
+    assert_eq!({ bond.max_payout_slot }, 2_000_000_000);
+    assert_eq!({ bond.slot }, 42);
+    assert_eq!({ bond.slot_payout }, 1_000_000_000);
+    // what v0 didn't have is zeroed, the version is set by the caller
+    assert_eq!({ bond.version }, 0);
+    assert_eq!({ bond.exit_penalty }, 0);
+    assert_eq!(bytemuck::bytes_of(&bond)[..data.len() - 8], data[8..]);
+
+    // and data already in the current layout isn't taken again
+    let mut current = bond;
+    current.version = reserve::BOND_VERSION;
+    assert!(migration_upgrade::<Bond>(bytemuck::bytes_of(&current)).is_err());
+}
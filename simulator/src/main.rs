@@ -0,0 +1,71 @@
+use anchor_lang::solana_program::program_stubs::{self, SyscallStubs};
+use anyhow::{Context, Result};
+use clap::Parser;
+use sim::{Params, Sim};
+use std::io::Write;
+use std::path::PathBuf;
+
+mod sim;
+#[cfg(test)]
+mod tests;
+
+#[derive(Parser)]
+#[clap(
+    name = "reserve-simulator",
+    about = "Simulate a reserve protocol parameter set, printing daily metrics as csv"
+)]
+struct Opts {
+    /// Parameter set, see params.example.toml
+    params: PathBuf,
+    /// Write the csv here instead of stdout
+    #[clap(short, long)]
+    out: Option<PathBuf>,
+}
+
+// the program's msg! logs print to stdout off-chain, where they'd mix into
+// the csv
+struct StderrLogs;
+
+impl SyscallStubs for StderrLogs {
+    fn sol_log(&self, message: &str) {
+        eprintln!("{}", message);
+    }
+}
+
+fn main() -> Result<()> {
+    let opts = Opts::parse();
+    program_stubs::set_syscall_stubs(Box::new(StderrLogs));
+    let data = std::fs::read_to_string(&opts.params)
+        .with_context(|| format!("reading {}", opts.params.display()))?;
+    let params: Params =
+        toml::from_str(&data).with_context(|| format!("parsing {}", opts.params.display()))?;
+    let rows = Sim::new(params).run()?;
+
+    let mut out: Box<dyn Write> = match &opts.out {
+        Some(path) => Box::new(std::fs::File::create(path)?),
+        None => Box::new(std::io::stdout()),
+    };
+    writeln!(
+        out,
+        "day,price,debt_ratio,backing_per_token,index,apy,runway_days,total_reserves,supply,staked,bonds,bonds_rejected"
+    )?;
+    for r in rows {
+        writeln!(
+            out,
+            "{},{:.6},{:.6},{:.6},{:.6},{:.4},{:.1},{:.3},{:.3},{:.3},{},{}",
+            r.day,
+            r.price,
+            r.debt_ratio,
+            r.backing_per_token,
+            r.index,
+            r.apy,
+            r.runway_days,
+            r.total_reserves,
+            r.supply,
+            r.staked,
+            r.bonds,
+            r.bonds_rejected
+        )?;
+    }
+    Ok(())
+}
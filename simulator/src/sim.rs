@@ -0,0 +1,261 @@
+use anyhow::{anyhow, bail, Result};
+use reserve::math::{
+    bond_quote, epoch_distribute, staking_rate_adjust, staking_shares, staking_value, BondQuote,
+    DAY, ONE,
+};
+use reserve::Bond;
+use serde::Deserialize;
+
+const STEP: u64 = 60 * 60;
+
+#[derive(Debug, Deserialize)]
+pub struct Params {
+    pub days: u64,
+    pub seed: u64,
+    pub treasury: TreasuryParams,
+    pub epoch: EpochParams,
+    pub bond: BondParams,
+    pub demand: DemandParams,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TreasuryParams {
+    // 1e9 percent of supply to distribute to stakers per epoch
+    pub staking_rate: u64,
+    // starting state
+    pub total_reserves: u64,
+    pub supply: u64,
+    pub staked: u64,
+}
+
+// same units as `epoch_configure`
+#[derive(Debug, Deserialize)]
+pub struct EpochParams {
+    pub length: u64,
+    pub adjustment_rate: u64,
+    pub adjustment_target: u64,
+}
+
+// same units as `bond_configure`
+#[derive(Debug, Deserialize)]
+pub struct BondParams {
+    pub token_decimals: u8,
+    pub vesting_period: u64,
+    pub rfv_rate: u64,
+    pub min_price: u64,
+    pub max_payout: u64,
+    pub max_debt: u64,
+    pub fee: u64,
+    pub bcv: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DemandParams {
+    pub bonds_per_day: f64,
+    // rfv per bond, drawn uniformly within +/- 50%
+    pub bond_size: f64,
+    pub stake_rate: f64,
+    pub unstake_rate: f64,
+}
+
+// one row of output per simulated day
+#[derive(Debug)]
+pub struct Row {
+    pub day: u64,
+    pub price: f64,
+    pub debt_ratio: f64,
+    pub backing_per_token: f64,
+    pub index: f64,
+    pub apy: f64,
+    pub runway_days: f64,
+    pub total_reserves: f64,
+    pub supply: f64,
+    pub staked: f64,
+    pub bonds: u64,
+    pub bonds_rejected: u64,
+}
+
+pub struct Sim {
+    params: Params,
+    rng: u64,
+    now: u64,
+    bond: Bond,
+    staking_rate: u64,
+    epoch_end: u64,
+    epoch_distribute: u64,
+    total_reserves: u64,
+    // mint_reserve supply, token_reserve_staking amount, mint_staking supply
+    supply: u64,
+    pool: u64,
+    shares: u64,
+    bonds: u64,
+    bonds_rejected: u64,
+}
+
+impl Sim {
+    pub fn new(params: Params) -> Sim {
+        let mut bond: Bond = bytemuck::Zeroable::zeroed();
+        bond.token_decimals = params.bond.token_decimals;
+        bond.vesting_period = params.bond.vesting_period;
+        bond.rfv_rate = params.bond.rfv_rate;
+        bond.min_price = params.bond.min_price;
+        bond.max_payout = params.bond.max_payout;
+        bond.max_debt = params.bond.max_debt;
+        bond.fee = params.bond.fee;
+        bond.bcv = params.bond.bcv;
+        Sim {
+            rng: params.seed.max(1),
+            now: 0,
+            bond,
+            staking_rate: params.treasury.staking_rate,
+            epoch_end: params.epoch.length,
+            epoch_distribute: 0,
+            total_reserves: params.treasury.total_reserves,
+            supply: params.treasury.supply,
+            pool: params.treasury.staked,
+            shares: params.treasury.staked,
+            bonds: 0,
+            bonds_rejected: 0,
+            params,
+        }
+    }
+
+    pub fn run(&mut self) -> Result<Vec<Row>> {
+        if self.params.epoch.length == 0 {
+            bail!("epoch length must be positive");
+        }
+        let mut rows = vec![];
+        for day in 1..=self.params.days {
+            let index_start = self.index();
+            self.bonds = 0;
+            self.bonds_rejected = 0;
+            for _ in 0..DAY / STEP {
+                self.step()?;
+            }
+            self.stake()?;
+            rows.push(self.row(day, index_start)?);
+        }
+        Ok(rows)
+    }
+
+    fn step(&mut self) -> Result<()> {
+        self.now += STEP;
+        while self.now >= self.epoch_end {
+            self.rebase()?;
+        }
+
+        let expected = self.params.demand.bonds_per_day * STEP as f64 / DAY as f64;
+        let mut count = expected.floor() as u64;
+        if self.random() < expected.fract() {
+            count += 1;
+        }
+        for _ in 0..count {
+            let size = self.params.demand.bond_size * (0.5 + self.random());
+            let amount = (size * 10_f64.powi(self.bond.token_decimals as i32)) as u64;
+            self.bond_deposit(amount)?;
+        }
+        Ok(())
+    }
+
+    // mirrors the end of an epoch in `staking_rebase_mint`
+    fn rebase(&mut self) -> Result<()> {
+        self.supply += self.epoch_distribute;
+        self.pool += self.epoch_distribute;
+        self.epoch_end += self.params.epoch.length;
+        if self.params.epoch.adjustment_rate > 0 {
+            self.staking_rate = staking_rate_adjust(
+                self.staking_rate,
+                self.params.epoch.adjustment_rate,
+                self.params.epoch.adjustment_target,
+            );
+        }
+        self.epoch_distribute =
+            epoch_distribute(self.supply, self.staking_rate).map_err(program_error)?;
+        Ok(())
+    }
+
+    // mirrors the checks and state changes of `bond_deposit`
+    fn bond_deposit(&mut self, amount: u64) -> Result<()> {
+        let q = self.quote(amount)?;
+        if q.total_debt >= self.bond.max_debt || q.payout <= ONE / 100 || q.payout > q.max_payout {
+            self.bonds_rejected += 1;
+            return Ok(());
+        }
+        let staked = staking_shares(q.payout, self.shares, self.pool).map_err(program_error)?;
+        self.bond.total_debt = q.total_debt + q.value;
+        self.bond.total_debt_last = self.now;
+        self.bond.total_debt_alltime += q.value;
+        self.total_reserves += q.value;
+        self.supply += q.payout + q.fee;
+        self.pool += q.payout;
+        self.shares += staked;
+        self.bonds += 1;
+        Ok(())
+    }
+
+    // daily staking and unstaking by holders
+    fn stake(&mut self) -> Result<()> {
+        let liquid = self.supply.saturating_sub(self.pool);
+        let amount = (liquid as f64 * self.params.demand.stake_rate) as u64;
+        if amount > 0 {
+            self.shares += staking_shares(amount, self.shares, self.pool).map_err(program_error)?;
+            self.pool += amount;
+        }
+        let shares = (self.shares as f64 * self.params.demand.unstake_rate) as u64;
+        if shares > 0 {
+            let value = staking_value(shares, self.shares, self.pool).map_err(program_error)?;
+            self.shares -= shares;
+            self.pool -= value;
+        }
+        Ok(())
+    }
+
+    fn row(&mut self, day: u64, index_start: f64) -> Result<Row> {
+        let q = self.quote(10_u64.pow(self.bond.token_decimals as u32))?;
+        let index = self.index();
+        let daily_rate = index / index_start - 1.0;
+        let runway_days = if self.total_reserves <= self.pool {
+            0.0
+        } else {
+            (self.total_reserves as f64 / self.pool as f64).ln() / (1.0 + daily_rate).ln()
+        };
+        Ok(Row {
+            day,
+            price: q.price as f64 / ONE as f64,
+            debt_ratio: q.debt_ratio as f64 / ONE as f64,
+            backing_per_token: self.total_reserves as f64 / self.supply.max(1) as f64,
+            index,
+            apy: (1.0 + daily_rate).powf(365.0) - 1.0,
+            runway_days,
+            total_reserves: self.total_reserves as f64 / ONE as f64,
+            supply: self.supply as f64 / ONE as f64,
+            staked: self.pool as f64 / ONE as f64,
+            bonds: self.bonds,
+            bonds_rejected: self.bonds_rejected,
+        })
+    }
+
+    fn quote(&self, amount: u64) -> Result<BondQuote> {
+        bond_quote(&self.bond, self.total_reserves, amount, self.now).map_err(program_error)
+    }
+
+    // reserve tokens per staking token
+    fn index(&self) -> f64 {
+        if self.shares == 0 {
+            return 1.0;
+        }
+        self.pool as f64 / self.shares as f64
+    }
+
+    // xorshift, uniform in [0, 1)
+    fn random(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+fn program_error(e: impl std::fmt::Debug) -> anyhow::Error {
+    anyhow!("program math failed: {:?}", e)
+}
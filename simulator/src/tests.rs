@@ -0,0 +1,87 @@
+use crate::sim::{Params, Row, Sim};
+
+fn params() -> Params {
+    let data = include_str!("../params.example.toml");
+    toml::from_str(data).unwrap()
+}
+
+fn run(params: Params) -> Vec<Row> {
+    Sim::new(params).run().unwrap()
+}
+
+#[test]
+fn run_returns_a_row_per_day() {
+    let rows = run(params());
+    assert_eq!(rows.len(), 90);
+    for (i, r) in rows.iter().enumerate() {
+        assert_eq!(r.day, i as u64 + 1);
+    }
+}
+
+#[test]
+fn run_is_deterministic_per_seed() {
+    let a = run(params());
+    let b = run(params());
+    assert_eq!(format!("{:?}", a), format!("{:?}", b));
+
+    let mut p = params();
+    p.seed = 2;
+    let c = run(p);
+    assert_ne!(format!("{:?}", a), format!("{:?}", c));
+}
+
+#[test]
+fn run_rejects_empty_epochs() {
+    let mut p = params();
+    p.epoch.length = 0;
+    assert!(Sim::new(p).run().is_err());
+}
+
+#[test]
+fn rebases_without_demand() {
+    let mut p = params();
+    p.days = 10;
+    p.demand.bonds_per_day = 0.0;
+    p.demand.stake_rate = 0.0;
+    p.demand.unstake_rate = 0.0;
+    let rows = run(p);
+
+    let reserves = 100_000.0;
+    for w in rows.windows(2) {
+        let (a, b) = (&w[0], &w[1]);
+        assert_eq!(b.bonds, 0);
+        assert_eq!(b.total_reserves, reserves);
+        // rebases mint to stakers only, growing supply and the index alike
+        assert!(b.supply > a.supply);
+        assert!(b.index > a.index);
+        assert!((b.supply - a.supply - (b.staked - a.staked)).abs() < 1e-6);
+        assert!(b.backing_per_token < a.backing_per_token);
+        assert!(b.runway_days < a.runway_days);
+    }
+    // 3 epochs a day at 0.1% of supply each
+    let last = rows.last().unwrap();
+    assert!(last.apy > 1.0 && last.apy < 100.0);
+}
+
+#[test]
+fn bonds_add_reserves_and_debt() {
+    let rows = run(params());
+    let first = &rows[0];
+    assert!(first.bonds > 0);
+    assert!(first.total_reserves > 100_000.0);
+    assert!(first.debt_ratio > 0.0);
+    // never below min_price
+    assert!(rows.iter().all(|r| r.price >= 1.0));
+}
+
+#[test]
+fn bonds_over_max_debt_are_rejected() {
+    let mut p = params();
+    p.days = 1;
+    // a bond without capacity
+    p.bond.max_debt = 0;
+    let rows = run(p);
+    assert_eq!(rows[0].bonds, 0);
+    assert!(rows[0].bonds_rejected > 0);
+    assert_eq!(rows[0].total_reserves, 100_000.0);
+}
@@ -0,0 +1,30 @@
+// Runs the simulator binary and checks nothing but csv reaches stdout,
+// as msg! logs from the program's math print there off-chain.
+
+use std::process::Command;
+
+const COLUMNS: usize = 12;
+
+#[test]
+fn stdout_is_csv() {
+    let params = concat!(env!("CARGO_MANIFEST_DIR"), "/params.example.toml");
+    let output = Command::new(env!("CARGO_BIN_EXE_reserve-simulator"))
+        .arg(params)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].starts_with("day,price,debt_ratio,"));
+    // header and a row per day of the example's 90
+    assert_eq!(lines.len(), 91);
+    for (i, line) in lines.iter().enumerate().skip(1) {
+        let fields: Vec<&str> = line.split(',').collect();
+        assert_eq!(fields.len(), COLUMNS, "line {}: {}", i, line);
+        assert_eq!(fields[0], i.to_string());
+        for field in fields {
+            assert!(field.parse::<f64>().is_ok(), "line {}: {}", i, line);
+        }
+    }
+}
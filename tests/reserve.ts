@@ -3,6 +3,7 @@ import chai, { expect } from 'chai';
 import * as anchor from '@project-serum/anchor';
 import { Program } from '@project-serum/anchor';
 import * as spl from '@solana/spl-token';
+import { createHash } from 'crypto';
 import { Reserve } from '../target/types/reserve';
 
 const { Keypair, PublicKey } = anchor.web3;
@@ -19,8 +20,13 @@ let mintUsdc, tokenUsdcUser, tokenUsdcTreasury;
 let mintReserve, mintStaking, tokenTreasuryAccount, tokenReserveDao, tokenReserveUser, tokenStakingUser;
 let treasuryKey, treasuryBump, mintReserveKey, mintReserveBump, mintStakingKey, mintStakingBump;
 let tokenReserveStakingKey, tokenReserveStakingBump, tokenStakingVestingKey, tokenStakingVestingBump;
+let epochKey, epochBump;
+let lockCurveKey, lockCurveBump;
 let bondKey, bondBump;
 let userKey, userBump;
+let roleKey, roleBump;
+let allowlistKey, allowlistBump, allowanceKey, allowanceBump, scheduleKey, scheduleBump;
+let observationsKey, observationsBump, oracleKey, oracleBump;
 
 describe('reserve', () => {
 
@@ -42,7 +48,8 @@ describe('reserve', () => {
     );
 
     // initialize treasury
-    const treasuryBaseKey = Keypair.generate().publicKey;
+    // fixed, tests/fixtures/bond_v0.json belongs to its treasury
+    const treasuryBaseKey = new PublicKey(Buffer.alloc(32, 1));
     [treasuryKey, treasuryBump] = await pda(["treasury", treasuryBaseKey]);
     [mintReserveKey, mintReserveBump] = await pda(["treasury_mint_reserve"]);
     [mintStakingKey, mintStakingBump] = await pda(["treasury_mint_staking"]);
@@ -71,6 +78,16 @@ describe('reserve', () => {
         }
       }
     );
+    [epochKey, epochBump] = await pda(["epoch", treasuryKey]);
+    [lockCurveKey, lockCurveBump] = await pda(["lock_curve", treasuryKey]);
+    await program.rpc.epochInitialize(epochBump, bn(1, 0), bn(Math.floor(Date.now() / 1000), 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        epoch: epochKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
     tokenUsdcTreasury = await mintUsdc.createAccount(treasuryKey);
     mintReserve = new spl.Token(program.provider.connection, mintReserveKey, spl.TOKEN_PROGRAM_ID, wallet.payer);
     mintStaking = new spl.Token(program.provider.connection, mintStakingKey, spl.TOKEN_PROGRAM_ID, wallet.payer);
@@ -79,17 +96,39 @@ describe('reserve', () => {
     tokenReserveUser = await mintReserve.createAccount(wallet.publicKey);
     tokenStakingUser = await mintStaking.createAccount(wallet.publicKey);
 
+    [roleKey, roleBump] = await pda(["role", treasuryKey, wallet.publicKey]);
+    await program.rpc.roleInitialize(roleBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        member: wallet.publicKey,
+        role: roleKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    await program.rpc.roleGrant(bn(15, 0), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey, role: roleKey }
+    });
+
     [bondKey, bondBump] = await pda(["bond", treasuryKey, mintUsdc.publicKey]);
     await program.rpc.bondInitialize(bondBump, {
       accounts: {
         signer: wallet.publicKey,
         treasury: treasuryKey,
+        role: roleKey,
         bond: bondKey,
         mintBond: mintUsdc.publicKey,
+        migration: (await pda(["migration", bondKey]))[0],
         systemProgram: anchor.web3.SystemProgram.programId,
       }
     });
 
+    [allowlistKey, allowlistBump] = await pda(["bond_allowlist", bondKey]);
+    [allowanceKey, allowanceBump] = await pda(["bond_allowance", bondKey, wallet.publicKey]);
+    [scheduleKey, scheduleBump] = await pda(["bond_schedule", bondKey]);
+    [observationsKey, observationsBump] = await pda(["bond_observations", bondKey]);
+    [oracleKey, oracleBump] = await pda(["oracle", treasuryKey]);
+
     [userKey, userBump] = await pda(["user", treasuryKey, wallet.publicKey]);
     await program.rpc.userInitialize(userBump, {
       accounts: {
@@ -107,11 +146,47 @@ describe('reserve', () => {
     expect(treasuryData.authority).to.eqPubkey(wallet.publicKey);
   });
 
+  it('treasuryMetadata', async () => {
+    // loaded into the test validator by Anchor.toml
+    const metadataProgram = new PublicKey('metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s');
+    const [metadataKey] = await pda(["metadata", metadataProgram, mintReserveKey], metadataProgram);
+    const accounts = {
+      signer: wallet.publicKey,
+      treasury: treasuryKey,
+      mint: mintReserveKey,
+      metadata: metadataKey,
+      metadataProgram,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+    await program.rpc.treasuryMetadata('Reserve', 'RSV', 'https://example.com/reserve.json', { accounts });
+    let info = await program.provider.connection.getAccountInfo(metadataKey);
+    expect(info.owner).to.eqPubkey(metadataProgram);
+    // key, update authority, mint
+    expect(new PublicKey(info.data.slice(1, 33))).to.eqPubkey(treasuryKey);
+    expect(new PublicKey(info.data.slice(33, 65))).to.eqPubkey(mintReserveKey);
+
+    await program.rpc.treasuryMetadata('Reserve Token', 'RSV', 'https://example.com/reserve.json', { accounts });
+    info = await program.provider.connection.getAccountInfo(metadataKey);
+    expect(info.data.includes(Buffer.from('Reserve Token'))).to.be.true;
+
+    const [stakingMetadataKey] = await pda(["metadata", metadataProgram, mintStakingKey], metadataProgram);
+    await program.rpc.treasuryMetadata('Staked Reserve', 'sRSV', 'https://example.com/sreserve.json', {
+      accounts: { ...accounts, mint: mintStakingKey, metadata: stakingMetadataKey },
+    });
+    info = await program.provider.connection.getAccountInfo(stakingMetadataKey);
+    expect(new PublicKey(info.data.slice(1, 33))).to.eqPubkey(treasuryKey);
+  });
+
   it('treasuryConfigure', async () => {
     await program.rpc.treasuryConfigure(
         daoKeypair.publicKey,
         wallet.publicKey,
         bn(100, 0), // staking_rate
+        bn(0, 0), // staking_warmup
+        bn(0, 0), // staking_cooldown
+        bn(0, 0), // lock_max_duration
+        bn(0, 0), // lock_boost_rate
         {
           accounts: {
             signer: wallet.publicKey,
@@ -123,6 +198,25 @@ describe('reserve', () => {
     expect(treasuryData.stakingRate).to.eqBN(bn(100, 0));
   });
 
+  it('epochConfigure', async () => {
+    await program.rpc.epochConfigure(
+      bn(1, 0), // length (1 second)
+      bn(10, 0), // adjustment_rate
+      bn(1000, 0), // adjustment_target
+      bn(0, 0), // gauge_budget
+      {
+        accounts: {
+          signer: wallet.publicKey,
+          treasury: treasuryKey,
+          epoch: epochKey,
+        }
+      }
+    );
+    const epochData = await program.account.epoch.fetch(epochKey);
+    expect(epochData.length).to.eqBN(bn(1, 0));
+    expect(epochData.adjustmentTarget).to.eqBN(bn(1000, 0));
+  });
+
   it('userInitialize', async () => {
     let userData = await program.account.user.fetch(userKey);
     expect(userData.signer).to.eqPubkey(wallet.publicKey);
@@ -147,6 +241,7 @@ describe('reserve', () => {
         accounts: {
           signer: wallet.publicKey,
           treasury: treasuryKey,
+          role: roleKey,
           bond: bondKey,
         }
       }
@@ -159,17 +254,53 @@ describe('reserve', () => {
     expect(bondData.maxPayout).to.eqBN(bn(10, 9));
     expect(bondData.maxDebt).to.eqBN(bn(100000, 9));
     expect(bondData.fee).to.eqBN(bn(5000, 0));
+
+    const accounts = { signer: wallet.publicKey, treasury: treasuryKey, role: roleKey, bond: bondKey };
+    let error;
+    try {
+      await program.rpc.bondConfigureExit(bn(2, 9), { accounts });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('invalid parameter');
+    await program.rpc.bondConfigureExit(bn(5, 8), { accounts });
+    bondData = await program.account.bond.fetch(bondKey);
+    expect(bondData.exitPenalty).to.eqBN(bn(5, 8));
+  });
+
+  it('bondLimit', async () => {
+    const accounts = { signer: wallet.publicKey, treasury: treasuryKey, role: roleKey, bond: bondKey };
+    let error;
+    try {
+      await program.rpc.bondLimit(bn(10, 9), bn(200000, 9), { accounts });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('invalid parameter');
+
+    await program.rpc.bondLimit(bn(10, 9), bn(100000, 9), { accounts });
+    await program.rpc.bondPause(true, { accounts });
+    let bondData = await program.account.bond.fetch(bondKey);
+    expect(bondData.paused.gt(bn(0))).to.be.true;
+    await program.rpc.bondPause(false, { accounts });
+    bondData = await program.account.bond.fetch(bondKey);
+    expect(bondData.paused).to.eqBN(bn(0));
   });
 
   it('bondDeposit', async () => {
     const treasuryData = await program.account.treasury.fetch(treasuryKey);
     const bondData = await program.account.bond.fetch(bondKey);
 
-    await program.rpc.bondDeposit(bn(300, 6), bn(1000000, 9), {
+    await program.rpc.bondDeposit(bn(300, 6), bn(1000000, 9), bn(0), [], {
       accounts: {
         signer: wallet.publicKey,
         treasury: treasuryKey,
         bond: bondKey,
+        allowlist: allowlistKey,
+        allowance: allowanceKey,
+        schedule: scheduleKey,
+        observations: observationsKey,
+        oracle: oracleKey,
         user: userKey,
         mintBond: mintUsdc.publicKey,
         mintReserve: mintReserve.publicKey,
@@ -205,37 +336,1394 @@ describe('reserve', () => {
     expect(userData.bonds[0].claimed.gt(bn(0))).to.be.true;
   });
 
-  it ('stakingWithdraw', async () => {
-    await program.rpc.stakingWithdraw(bn(1000, 0), {
+  it('treasuryTransfer', async () => {
+    let treasuryData = await program.account.treasury.fetch(treasuryKey);
+    const totalReserves = treasuryData.totalReserves;
+    const accounts = {
+      signer: wallet.publicKey,
+      treasury: treasuryKey,
+      role: roleKey,
+      bond: bondKey,
+      tokenTreasury: tokenUsdcTreasury,
+      tokenDestination: tokenUsdcUser,
+      tokenProgram: spl.TOKEN_PROGRAM_ID,
+    };
+    await program.rpc.treasuryTransfer(bn(1, 6), { accounts });
+    treasuryData = await program.account.treasury.fetch(treasuryKey);
+    expect(treasuryData.totalReserves).to.eqBN(totalReserves.sub(bn(1, 9)));
+
+    // not a bond's token account
+    let error;
+    try {
+      await program.rpc.treasuryTransfer(bn(1, 0), {
+        accounts: {
+          ...accounts,
+          tokenTreasury: treasuryData.tokenReserveStaking,
+          tokenDestination: tokenReserveDao,
+        }
+      });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('unauthorized');
+  });
+
+  it('referrerDeposit', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    const [referrerKey, referrerBump] = await pda(["referrer", treasuryKey, wallet.publicKey]);
+    await program.rpc.referrerInitialize(referrerBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        referrer: referrerKey,
+        tokenReserve: tokenReserveUser,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    let error;
+    try {
+      await program.rpc.referrerConfigure(bn(5, 8), {
+        accounts: { signer: wallet.publicKey, referrer: referrerKey }
+      });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('invalid parameter');
+    await program.rpc.referrerApprove(bn(5, 8), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey, referrer: referrerKey }
+    });
+    await program.rpc.referrerConfigure(bn(5, 8), {
+      accounts: { signer: wallet.publicKey, referrer: referrerKey }
+    });
+
+    const before = (await mintReserve.getAccountInfo(tokenReserveUser)).amount;
+    await program.rpc.bondDeposit(bn(100, 6), bn(1000000, 9), bn(0), [], {
       accounts: {
         signer: wallet.publicKey,
         treasury: treasuryKey,
+        bond: bondKey,
+        allowlist: allowlistKey,
+        allowance: allowanceKey,
+        schedule: scheduleKey,
+        observations: observationsKey,
+        oracle: oracleKey,
+        user: userKey,
+        mintBond: mintUsdc.publicKey,
         mintReserve: mintReserve.publicKey,
         mintStaking: mintStaking.publicKey,
-        tokenReserveUser: tokenReserveUser,
-        tokenReserveStaking: tokenReserveStakingKey,
+        tokenBondUser: tokenUsdcUser,
+        tokenBondTreasury: tokenUsdcTreasury,
+        tokenReserveDao: tokenReserveDao,
+        tokenReserveStaking: treasuryData.tokenReserveStaking,
+        tokenStakingVesting: treasuryData.tokenStakingVesting,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      },
+      remainingAccounts: [
+        { pubkey: referrerKey, isSigner: false, isWritable: true },
+        { pubkey: tokenReserveUser, isSigner: false, isWritable: true },
+      ],
+    });
+    const referrerData = await program.account.referrer.fetch(referrerKey);
+    expect(referrerData.deposits).to.eqBN(bn(1, 0));
+    expect(referrerData.volume).to.eqBN(bn(100, 9));
+    expect(referrerData.earned.gt(bn(0))).to.be.true;
+    const after = (await mintReserve.getAccountInfo(tokenReserveUser)).amount;
+    expect(after.sub(before)).to.eqBN(referrerData.earned);
+  });
+
+  it('bondConfigureCaps', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    const configureAccounts = { signer: wallet.publicKey, treasury: treasuryKey, role: roleKey, bond: bondKey };
+    const deposit = () => program.rpc.bondDeposit(bn(100, 6), bn(1000000, 9), bn(0), [], {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        bond: bondKey,
+        allowlist: allowlistKey,
+        allowance: allowanceKey,
+        schedule: scheduleKey,
+        observations: observationsKey,
+        oracle: oracleKey,
+        user: userKey,
+        mintBond: mintUsdc.publicKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenBondUser: tokenUsdcUser,
+        tokenBondTreasury: tokenUsdcTreasury,
+        tokenReserveDao: tokenReserveDao,
+        tokenReserveStaking: treasuryData.tokenReserveStaking,
+        tokenStakingVesting: treasuryData.tokenStakingVesting,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+
+    // the referrer deposit was counted against its slot
+    let bondData = await program.account.bond.fetch(bondKey);
+    expect(bondData.slot.gt(bn(0))).to.be.true;
+    expect(bondData.slotPayout.gt(bn(0))).to.be.true;
+
+    await program.rpc.bondConfigureCaps(bn(450, 9), bn(0), { accounts: configureAccounts });
+    let error;
+    try {
+      await deposit();
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('bond user at max debt');
+
+    // a 100 usdc deposit pays out more than 1 reserve token
+    await program.rpc.bondConfigureCaps(bn(0), bn(1, 9), { accounts: configureAccounts });
+    error = undefined;
+    try {
+      await deposit();
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('bond at max payout for this slot');
+    const after = await program.account.bond.fetch(bondKey);
+    expect(after.slot).to.eqBN(bondData.slot);
+    expect(after.slotPayout).to.eqBN(bondData.slotPayout);
+
+    await program.rpc.bondConfigureCaps(bn(0), bn(0), { accounts: configureAccounts });
+  });
+
+  it('bondAllowlist', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    const leaf = (signer, maxAmount) =>
+      sha256(signer.toBuffer(), maxAmount.toArrayLike(Buffer, 'le', 8));
+    const node = (a, b) => Buffer.compare(a, b) <= 0 ? sha256(a, b) : sha256(b, a);
+    const leafUser = leaf(wallet.publicKey, bn(150, 6));
+    const leafOther = leaf(Keypair.generate().publicKey, bn(1000, 6));
+    const root = node(leafUser, leafOther);
+
+    await program.rpc.bondAllowlistInitialize(allowlistBump, [...root], {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        role: roleKey,
+        bond: bondKey,
+        allowlist: allowlistKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    await program.rpc.bondAllowanceInitialize(allowanceBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        bond: bondKey,
+        allowance: allowanceKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+
+    const deposit = (amount, maxAmount, proof) => program.rpc.bondDeposit(amount, bn(1000000, 9), maxAmount, proof, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        bond: bondKey,
+        allowlist: allowlistKey,
+        allowance: allowanceKey,
+        schedule: scheduleKey,
+        observations: observationsKey,
+        oracle: oracleKey,
+        user: userKey,
+        mintBond: mintUsdc.publicKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenBondUser: tokenUsdcUser,
+        tokenBondTreasury: tokenUsdcTreasury,
+        tokenReserveDao: tokenReserveDao,
+        tokenReserveStaking: treasuryData.tokenReserveStaking,
+        tokenStakingVesting: treasuryData.tokenStakingVesting,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+    let error;
+    try {
+      await deposit(bn(100, 6), bn(1000, 6), [[...leafOther]]);
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('wallet not allowlisted for bond');
+    await deposit(bn(100, 6), bn(150, 6), [[...leafOther]]);
+    error = undefined;
+    try {
+      await deposit(bn(100, 6), bn(150, 6), [[...leafOther]]);
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('bond allowance exceeded');
+    const allowanceData = await program.account.bondAllowance.fetch(allowanceKey);
+    expect(allowanceData.used).to.eqBN(bn(100, 6));
+
+    await program.rpc.bondAllowlistConfigure(new Array(32).fill(0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        role: roleKey,
+        bond: bondKey,
+        allowlist: allowlistKey,
+      }
+    });
+  });
+
+  it('bondWithdraw position', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    const key = Keypair.generate().publicKey;
+    const [mintKey, mintBump] = await pda(["bond_position_mint", treasuryKey, key]);
+    const [positionKey, positionBump] = await pda(["bond_position", mintKey]);
+    const [tokenPositionKey, tokenPositionBump] = await pda(["bond_position_token", mintKey]);
+    await program.rpc.bondTokenize(positionBump, mintBump, tokenPositionBump, key, bn(1, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        user: userKey,
+        mint: mintKey,
+        position: positionKey,
+        tokenPosition: tokenPositionKey,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    // the slot stays taken, so its debt is still the user's
+    const userData = await program.account.user.fetch(userKey);
+    expect(userData.bonds[1].bond).to.eqPubkey(bondKey);
+    expect(userData.bonds[1].payout.gt(bn(0))).to.be.true;
+    expect(userData.bonds[1].staked).to.eqBN(bn(0));
+    const mintPosition = new spl.Token(program.provider.connection, mintKey, spl.TOKEN_PROGRAM_ID, wallet.payer);
+    const mintInfo = await mintPosition.getMintInfo();
+    expect(mintInfo.supply).to.eqBN(bn(1, 0));
+    expect(mintInfo.mintAuthority).to.be.null;
+
+    let error;
+    try {
+      await program.rpc.bondExitEarly(bn(1, 0), {
+        accounts: {
+          signer: wallet.publicKey,
+          treasury: treasuryKey,
+          bond: bondKey,
+          user: userKey,
+          mintStaking: mintStaking.publicKey,
+          tokenStakingVesting: treasuryData.tokenStakingVesting,
+          tokenStakingUser: tokenStakingUser,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        }
+      });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('bond position tokenized');
+
+    await new Promise(resolve => setTimeout(resolve, 2000));
+    await program.rpc.bondWithdraw(bn(0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        user: positionKey,
+        mintStaking: mintStaking.publicKey,
+        tokenStakingVesting: treasuryData.tokenStakingVesting,
+        tokenStakingUser: tokenStakingUser,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      },
+      remainingAccounts: [
+        { pubkey: mintKey, isSigner: false, isWritable: true },
+        { pubkey: tokenPositionKey, isSigner: false, isWritable: true },
+      ],
+    });
+    const positionData = await program.account.bondPosition.fetch(positionKey);
+    expect(positionData.bond.claimed.gt(bn(0))).to.be.true;
+  });
+
+  it('bondExitEarly', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    const supplyBefore = (await mintStaking.getMintInfo()).supply;
+    const balanceBefore = (await mintStaking.getAccountInfo(tokenStakingUser)).amount;
+    let userData = await program.account.user.fetch(userKey);
+    const staked = userData.bonds[2].staked;
+    await program.rpc.bondExitEarly(bn(2, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        bond: bondKey,
+        user: userKey,
+        mintStaking: mintStaking.publicKey,
+        tokenStakingVesting: treasuryData.tokenStakingVesting,
         tokenStakingUser: tokenStakingUser,
-        tokenStakingVesting: tokenStakingVestingKey,
         tokenProgram: spl.TOKEN_PROGRAM_ID,
       }
     });
+    const debt = userData.debt;
+    userData = await program.account.user.fetch(userKey);
+    expect(userData.bonds[2].staked).to.eqBN(bn(0));
+    const penalty = supplyBefore.sub((await mintStaking.getMintInfo()).supply);
+    const released = (await mintStaking.getAccountInfo(tokenStakingUser)).amount.sub(balanceBefore);
+    expect(penalty.gt(bn(0))).to.be.true;
+    expect(released.add(penalty)).to.eqBN(staked);
+
+    // the exited deposit still counts towards max_debt_user, a 100 usdc
+    // deposit doesn't fit in the room left
+    expect(userData.debt).to.eqBN(debt);
+    const configureAccounts = { signer: wallet.publicKey, treasury: treasuryKey, role: roleKey, bond: bondKey };
+    await program.rpc.bondConfigureCaps(debt.add(bn(50, 9)), bn(0), { accounts: configureAccounts });
+    let error;
+    try {
+      await program.rpc.bondDeposit(bn(100, 6), bn(1000000, 9), bn(0), [], {
+        accounts: {
+          signer: wallet.publicKey,
+          treasury: treasuryKey,
+          bond: bondKey,
+          allowlist: allowlistKey,
+          allowance: allowanceKey,
+          schedule: scheduleKey,
+          observations: observationsKey,
+          oracle: oracleKey,
+          user: userKey,
+          mintBond: mintUsdc.publicKey,
+          mintReserve: mintReserve.publicKey,
+          mintStaking: mintStaking.publicKey,
+          tokenBondUser: tokenUsdcUser,
+          tokenBondTreasury: tokenUsdcTreasury,
+          tokenReserveDao: tokenReserveDao,
+          tokenReserveStaking: treasuryData.tokenReserveStaking,
+          tokenStakingVesting: treasuryData.tokenStakingVesting,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        }
+      });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('bond user at max debt');
+    await program.rpc.bondConfigureCaps(bn(0), bn(0), { accounts: configureAccounts });
   });
 
-  it ('stakingDeposit', async () => {
-    await program.rpc.stakingDeposit(bn(1000, 0), {
+  it('bondSchedule', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    await program.rpc.bondScheduleInitialize(scheduleBump, bn(1, 0), bn(1800, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        role: roleKey,
+        bond: bondKey,
+        schedule: scheduleKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    await program.rpc.bondDeposit(bn(100, 6), bn(1000000, 9), bn(0), [], {
       accounts: {
         signer: wallet.publicKey,
         treasury: treasuryKey,
+        bond: bondKey,
+        allowlist: allowlistKey,
+        allowance: allowanceKey,
+        schedule: scheduleKey,
+        observations: observationsKey,
+        oracle: oracleKey,
+        user: userKey,
+        mintBond: mintUsdc.publicKey,
         mintReserve: mintReserve.publicKey,
         mintStaking: mintStaking.publicKey,
-        tokenReserveUser: tokenReserveUser,
-        tokenReserveStaking: tokenReserveStakingKey,
+        tokenBondUser: tokenUsdcUser,
+        tokenBondTreasury: tokenUsdcTreasury,
+        tokenReserveDao: tokenReserveDao,
+        tokenReserveStaking: treasuryData.tokenReserveStaking,
+        tokenStakingVesting: treasuryData.tokenStakingVesting,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+    let userData = await program.account.user.fetch(userKey);
+    expect(userData.bonds[2].vestingKind).to.eqBN(bn(1, 0));
+    expect(userData.bonds[2].vestingCliff).to.eqBN(bn(1800, 0));
+
+    // before the cliff, nothing is claimable
+    await new Promise(resolve => setTimeout(resolve, 2000));
+    await program.rpc.bondWithdraw(bn(2, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        user: userKey,
+        mintStaking: mintStaking.publicKey,
+        tokenStakingVesting: treasuryData.tokenStakingVesting,
+        tokenStakingUser: tokenStakingUser,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+    userData = await program.account.user.fetch(userKey);
+    expect(userData.bonds[2].claimed).to.eqBN(bn(0));
+
+    await program.rpc.bondScheduleConfigure(bn(0, 0), bn(0, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        role: roleKey,
+        bond: bondKey,
+        schedule: scheduleKey,
+      }
+    });
+  });
+
+  it('bondPositionExitEarly', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    const key = Keypair.generate().publicKey;
+    const [mintKey, mintBump] = await pda(["bond_position_mint", treasuryKey, key]);
+    const [positionKey, positionBump] = await pda(["bond_position", mintKey]);
+    const [tokenPositionKey, tokenPositionBump] = await pda(["bond_position_token", mintKey]);
+    const staked = (await program.account.user.fetch(userKey)).bonds[2].staked;
+    await program.rpc.bondTokenize(positionBump, mintBump, tokenPositionBump, key, bn(2, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        user: userKey,
+        mint: mintKey,
+        position: positionKey,
+        tokenPosition: tokenPositionKey,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+
+    // still before the cliff, half of it is burned
+    const supplyBefore = (await mintStaking.getMintInfo()).supply;
+    const balanceBefore = (await mintStaking.getAccountInfo(tokenStakingUser)).amount;
+    await program.rpc.bondPositionExitEarly({
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        bond: bondKey,
+        position: positionKey,
+        mint: mintKey,
+        tokenPosition: tokenPositionKey,
+        mintStaking: mintStaking.publicKey,
+        tokenStakingVesting: treasuryData.tokenStakingVesting,
         tokenStakingUser: tokenStakingUser,
         tokenProgram: spl.TOKEN_PROGRAM_ID,
       }
     });
+    const penalty = supplyBefore.sub((await mintStaking.getMintInfo()).supply);
+    const released = (await mintStaking.getAccountInfo(tokenStakingUser)).amount.sub(balanceBefore);
+    expect(penalty).to.eqBN(staked.div(bn(2, 0)));
+    expect(released.add(penalty)).to.eqBN(staked);
+    expect(await program.provider.connection.getAccountInfo(positionKey)).to.be.null;
+    const mintPosition = new spl.Token(program.provider.connection, mintKey, spl.TOKEN_PROGRAM_ID, wallet.payer);
+    expect((await mintPosition.getMintInfo()).supply).to.eqBN(bn(0));
   });
-});
+
+  it('feeSplitDistribute', async () => {
+    const [feeSplitKey, feeSplitBump] = await pda(["fee_split", treasuryKey]);
+    await program.rpc.feeSplitInitialize(feeSplitBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        feeSplit: feeSplitKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    const accounts = { signer: wallet.publicKey, treasury: treasuryKey, feeSplit: feeSplitKey };
+    let error;
+    try {
+      await program.rpc.feeSplitConfigure([wallet.publicKey], [bn(5000, 0)], { accounts });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('invalid parameter');
+    await program.rpc.feeSplitConfigure(
+      [wallet.publicKey, daoKeypair.publicKey],
+      [bn(6000, 0), bn(4000, 0)],
+      { accounts }
+    );
+
+    const tokenVault = await mintUsdc.createAccount(feeSplitKey);
+    const tokenUsdcDao = await mintUsdc.createAccount(daoKeypair.publicKey);
+    await mintUsdc.mintTo(tokenVault, mintUsdcAuthority, [], bn(10, 6).toString());
+    const before = (await mintUsdc.getAccountInfo(tokenUsdcUser)).amount;
+    await program.rpc.feeSplitDistribute({
+      accounts: {
+        feeSplit: feeSplitKey,
+        tokenVault: tokenVault,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      },
+      remainingAccounts: [
+        { pubkey: tokenUsdcUser, isSigner: false, isWritable: true },
+        { pubkey: tokenUsdcDao, isSigner: false, isWritable: true },
+      ],
+    });
+    const after = (await mintUsdc.getAccountInfo(tokenUsdcUser)).amount;
+    expect(after.sub(before)).to.eqBN(bn(6, 6));
+    expect((await mintUsdc.getAccountInfo(tokenUsdcDao)).amount).to.eqBN(bn(4, 6));
+    expect((await mintUsdc.getAccountInfo(tokenVault)).amount).to.eqBN(bn(0));
+  });
+
+  it ('stakingWithdraw', async () => {
+    await program.rpc.stakingWithdraw(bn(1000, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        epoch: epochKey,
+        lockCurve: lockCurveKey,
+        user: userKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenReserveUser: tokenReserveUser,
+        tokenReserveStaking: tokenReserveStakingKey,
+        tokenStakingUser: tokenStakingUser,
+        tokenStakingVesting: tokenStakingVestingKey,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+  });
+
+  it ('stakingDeposit', async () => {
+    await program.rpc.stakingDeposit(bn(1000, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        epoch: epochKey,
+        lockCurve: lockCurveKey,
+        user: userKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenReserveUser: tokenReserveUser,
+        tokenReserveStaking: tokenReserveStakingKey,
+        tokenStakingUser: tokenStakingUser,
+        tokenStakingVesting: tokenStakingVestingKey,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+  });
+
+  it ('stakingRewards', async () => {
+    const before = await program.account.treasury.fetch(treasuryKey);
+    await new Promise(resolve => setTimeout(resolve, 1000));
+    await program.rpc.stakingRewards({
+      accounts: {
+        treasury: treasuryKey,
+        epoch: epochKey,
+        lockCurve: lockCurveKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenReserveStaking: tokenReserveStakingKey,
+        tokenStakingVesting: tokenStakingVestingKey,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+    const after = await program.account.treasury.fetch(treasuryKey);
+    expect(after.stakingLast.gt(before.stakingLast)).to.be.true;
+    const epochData = await program.account.epoch.fetch(epochKey);
+    expect(epochData.number.gt(bn(0))).to.be.true;
+  });
+
+  it ('stakingRedeem', async () => {
+    await program.rpc.treasuryConfigure(daoKeypair.publicKey, wallet.publicKey, bn(100, 0), bn(0, 0), bn(3600, 0), bn(0, 0), bn(0, 0), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey }
+    });
+    await program.rpc.stakingWithdraw(bn(1000, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        epoch: epochKey,
+        lockCurve: lockCurveKey,
+        user: userKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenReserveUser: tokenReserveUser,
+        tokenReserveStaking: tokenReserveStakingKey,
+        tokenStakingUser: tokenStakingUser,
+        tokenStakingVesting: tokenStakingVestingKey,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+    const userData = await program.account.user.fetch(userKey);
+    expect(userData.cooldownAmount.gt(bn(0))).to.be.true;
+
+    let error;
+    try {
+      await program.rpc.stakingRedeem({
+        accounts: {
+          signer: wallet.publicKey,
+          treasury: treasuryKey,
+          user: userKey,
+          tokenReserveUser: tokenReserveUser,
+          tokenReserveStaking: tokenReserveStakingKey,
+          tokenProgram: spl.TOKEN_PROGRAM_ID,
+        }
+      });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('staking cooldown not over');
+
+    await program.rpc.treasuryConfigure(daoKeypair.publicKey, wallet.publicKey, bn(100, 0), bn(0, 0), bn(0, 0), bn(0, 0), bn(0, 0), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey }
+    });
+  });
+
+  it ('lockDeposit', async () => {
+    await program.rpc.treasuryConfigure(daoKeypair.publicKey, wallet.publicKey, bn(100, 0), bn(0, 0), bn(0, 0), bn(3600, 0), bn(100000000, 0), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey }
+    });
+    await program.rpc.stakingDeposit(bn(1000, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        epoch: epochKey,
+        lockCurve: lockCurveKey,
+        user: userKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenReserveUser: tokenReserveUser,
+        tokenReserveStaking: tokenReserveStakingKey,
+        tokenStakingUser: tokenStakingUser,
+        tokenStakingVesting: tokenStakingVestingKey,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+
+    await program.rpc.lockCurveInitialize(lockCurveBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        lockCurve: lockCurveKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+
+    const [lockKey, lockBump] = await pda(["lock", treasuryKey, wallet.publicKey]);
+    await program.rpc.lockInitialize(lockBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        lock: lockKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    const lockAccounts = {
+      signer: wallet.publicKey,
+      treasury: treasuryKey,
+      lockCurve: lockCurveKey,
+      lock: lockKey,
+      mintStaking: mintStaking.publicKey,
+      tokenStakingUser: tokenStakingUser,
+      tokenStakingVesting: tokenStakingVestingKey,
+      tokenProgram: spl.TOKEN_PROGRAM_ID,
+    };
+    await program.rpc.lockDeposit(bn(500, 0), bn(Math.floor(Date.now() / 1000) + 1800, 0), {
+      accounts: lockAccounts,
+    });
+    const lockData = await program.account.lock.fetch(lockKey);
+    expect(lockData.amount).to.eqBN(bn(500, 0));
+    expect(lockData.slope.gt(bn(0))).to.be.true;
+    // rounded down to the curve's period
+    const curveData = await program.account.lockCurve.fetch(lockCurveKey);
+    expect(lockData.end.mod(curveData.period)).to.eqBN(bn(0));
+    expect(curveData.slope).to.eqBN(lockData.slope);
+    expect(curveData.bias).to.eqBN(lockData.slope.mul(lockData.end));
+
+    let error;
+    try {
+      await program.rpc.lockWithdraw({ accounts: lockAccounts });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('lock not over');
+  });
+
+  it ('proposalExecute', async () => {
+    await program.rpc.governanceConfigure(bn(1, 0), bn(500000000, 0), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey }
+    });
+    // locks can only vote on proposals started after their last change
+    await new Promise(resolve => setTimeout(resolve, 1100));
+
+    const [lockKey] = await pda(["lock", treasuryKey, wallet.publicKey]);
+    const proposalBaseKey = Keypair.generate().publicKey;
+    const [proposalKey, proposalBump] = await pda(["proposal", treasuryKey, proposalBaseKey]);
+    const bondData = await program.account.bond.fetch(bondKey);
+    await program.rpc.proposalCreate(proposalBump, proposalBaseKey, [
+      { epochConfigure: { params: { length: bn(1, 0), adjustmentRate: bn(0, 0), adjustmentTarget: bn(5, 0), gaugeBudget: bn(0, 0) } } },
+      { bondConfigure: { bond: bondKey, params: {
+        vestingPeriod: bondData.vestingPeriod,
+        rfvRate: bondData.rfvRate,
+        minPrice: bondData.minPrice,
+        maxPayout: bondData.maxPayout.add(bn(1, 0)),
+        maxDebt: bondData.maxDebt,
+        fee: bondData.fee,
+        bcv: bondData.bcv,
+      } } },
+    ], {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        epoch: epochKey,
+        lock: lockKey,
+        proposal: proposalKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+
+    const [voteKey, voteBump] = await pda(["vote", proposalKey, lockKey]);
+    await program.rpc.proposalVote(voteBump, true, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        proposal: proposalKey,
+        lock: lockKey,
+        vote: voteKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    const proposalData = await program.account.proposal.fetch(proposalKey);
+    expect(proposalData.votesFor.gt(bn(0))).to.be.true;
+
+    const wait = proposalData.end.toNumber() * 1000 - Date.now() + 1500;
+    await new Promise(resolve => setTimeout(resolve, Math.max(wait, 0)));
+    // bond actions need the bond manager role, granted to the treasury's
+    // address for proposals
+    const [governanceRoleKey, governanceRoleBump] = await pda(["role", treasuryKey, treasuryKey]);
+    const executeAccounts = {
+      accounts: {
+        treasury: treasuryKey,
+        epoch: epochKey,
+        proposal: proposalKey,
+      },
+      remainingAccounts: [
+        { pubkey: bondKey, isWritable: true, isSigner: false },
+        { pubkey: governanceRoleKey, isWritable: false, isSigner: false },
+      ],
+    };
+    await program.rpc.roleInitialize(governanceRoleBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        member: treasuryKey,
+        role: governanceRoleKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    let error;
+    try {
+      await program.rpc.proposalExecute(executeAccounts);
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('unauthorized');
+
+    await program.rpc.roleGrant(bn(1, 0), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey, role: governanceRoleKey }
+    });
+    await program.rpc.proposalExecute(executeAccounts);
+    const epochData = await program.account.epoch.fetch(epochKey);
+    expect(epochData.adjustmentTarget).to.eqBN(bn(5, 0));
+    expect((await program.account.bond.fetch(bondKey)).maxPayout).to.eqBN(bondData.maxPayout.add(bn(1, 0)));
+  });
+
+  it ('bondGaugeApply', async () => {
+    await program.rpc.epochConfigure(bn(1, 0), bn(0, 0), bn(5, 0), bn(1000, 9), bn(5, 8), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey, epoch: epochKey }
+    });
+    const [lockKey] = await pda(["lock", treasuryKey, wallet.publicKey]);
+    const number = (await program.account.epoch.fetch(epochKey)).number;
+    const [gaugeVoteKey, gaugeVoteBump] = await pda(["gauge_vote", lockKey, number.toArrayLike(Buffer, 'le', 8)]);
+    await program.rpc.gaugeVote(gaugeVoteBump, number, [bn(1, 9)], {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        epoch: epochKey,
+        lock: lockKey,
+        gaugeVote: gaugeVoteKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      },
+      remainingAccounts: [{ pubkey: bondKey, isWritable: true, isSigner: false }],
+    });
+    let bondData = await program.account.bond.fetch(bondKey);
+    expect(bondData.gaugeVotes.gt(bn(0))).to.be.true;
+
+    // votes apply once the epoch they were cast in is over
+    await new Promise(resolve => setTimeout(resolve, 1100));
+    await program.rpc.stakingRewards({
+      accounts: {
+        treasury: treasuryKey,
+        epoch: epochKey,
+        lockCurve: lockCurveKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenReserveStaking: tokenReserveStakingKey,
+        tokenStakingVesting: tokenStakingVestingKey,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+    await program.rpc.bondGaugeApply({
+      accounts: { treasury: treasuryKey, epoch: epochKey, bond: bondKey }
+    });
+    bondData = await program.account.bond.fetch(bondKey);
+    expect(bondData.maxDebt).to.eqBN(bn(1000, 9));
+  });
+
+  it ('lockClaim', async () => {
+    // boosted by the rebase in bondGaugeApply without the lock being touched
+    const curveData = await program.account.lockCurve.fetch(lockCurveKey);
+    expect(curveData.rewardPerWeight.gt(bn(0))).to.be.true;
+
+    const [lockKey] = await pda(["lock", treasuryKey, wallet.publicKey]);
+    const before = (await mintStaking.getAccountInfo(tokenStakingUser)).amount;
+    await program.rpc.lockClaim({
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        lockCurve: lockCurveKey,
+        lock: lockKey,
+        mintStaking: mintStaking.publicKey,
+        tokenStakingUser: tokenStakingUser,
+        tokenStakingVesting: tokenStakingVestingKey,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+    const after = (await mintStaking.getAccountInfo(tokenStakingUser)).amount;
+    expect(after.gt(before)).to.be.true;
+  });
+
+  it ('multisigExecute', async () => {
+    const [multisigKey, multisigBump] = await pda(["multisig", treasuryKey]);
+    const [multisigSignerKey] = await pda(["multisig_signer", multisigKey]);
+    await program.rpc.multisigCreate(multisigBump, [wallet.publicKey], bn(1, 0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        multisig: multisigKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    await program.rpc.treasuryConfigure(daoKeypair.publicKey, multisigSignerKey, bn(100, 0), bn(0, 0), bn(0, 0), bn(3600, 0), bn(100000000, 0), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey }
+    });
+
+    // hand authority back to the wallet through the multisig
+    const data = program.coder.instruction.encode('treasuryConfigure', {
+      dao: daoKeypair.publicKey,
+      authority: wallet.publicKey,
+      stakingRate: bn(100, 0),
+      stakingWarmup: bn(0, 0),
+      stakingCooldown: bn(0, 0),
+      lockMaxDuration: bn(3600, 0),
+      lockBoostRate: bn(100000000, 0),
+    });
+    const metas = [
+      { pubkey: multisigSignerKey, isSigner: true, isWritable: false },
+      { pubkey: treasuryKey, isSigner: false, isWritable: true },
+    ];
+    const transactionBaseKey = Keypair.generate().publicKey;
+    const [transactionKey, transactionBump] = await pda(["multisig_transaction", multisigKey, transactionBaseKey]);
+    await program.rpc.multisigTransactionCreate(transactionBump, transactionBaseKey, metas, data, {
+      accounts: {
+        signer: wallet.publicKey,
+        multisig: multisigKey,
+        transaction: transactionKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    await program.rpc.multisigExecute({
+      accounts: { multisig: multisigKey, transaction: transactionKey },
+      remainingAccounts: metas.map(m => ({ ...m, isSigner: false })).concat([
+        { pubkey: programId, isSigner: false, isWritable: false },
+      ]),
+    });
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    expect(treasuryData.authority).to.eqPubkey(wallet.publicKey);
+  });
+
+  it ('marketDeposit', async () => {
+    const [marketConfigKey, marketConfigBump] = await pda(["market_config", treasuryKey]);
+    await program.rpc.marketConfigInitialize(marketConfigBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        marketConfig: marketConfigKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    await program.rpc.marketConfigConfigure(bn(10000000, 0), {
+      accounts: { signer: wallet.publicKey, treasury: treasuryKey, marketConfig: marketConfigKey }
+    });
+
+    // an issuer selling its own token for usdc
+    const mintPayout = await spl.Token.createMint(
+      program.provider.connection,
+      wallet.payer,
+      wallet.publicKey,
+      null,
+      6,
+      spl.TOKEN_PROGRAM_ID
+    );
+    const tokenPayoutIssuer = await mintPayout.createAccount(wallet.publicKey);
+    await mintPayout.mintTo(tokenPayoutIssuer, wallet.payer, [], bn(1000000, 6).toString());
+
+    const marketBaseKey = Keypair.generate().publicKey;
+    const [marketKey, marketBump] = await pda(["market", treasuryKey, marketBaseKey]);
+    const [tokenPayoutKey, tokenPayoutBump] = await pda(["market_token_payout", marketKey]);
+    await program.rpc.marketCreate(marketBump, tokenPayoutBump, marketBaseKey, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        market: marketKey,
+        mintQuote: mintUsdc.publicKey,
+        mintPayout: mintPayout.publicKey,
+        tokenPayout: tokenPayoutKey,
+        tokenQuote: tokenUsdcUser,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    await mintPayout.transfer(tokenPayoutIssuer, tokenPayoutKey, wallet.payer, [], bn(1000, 6).toString());
+    await program.rpc.marketConfigure(
+      bn(3600, 0), // vesting_period
+      bn(1, 9), // rfv_rate
+      bn(1, 9), // min_price
+      bn(10, 9), // max_payout
+      bn(100000, 9), // max_debt
+      bn(500, 0), // bcv
+      { accounts: { signer: wallet.publicKey, market: marketKey } }
+    );
+
+    const treasuryBefore = await mintUsdc.getAccountInfo(tokenUsdcTreasury);
+    const positionBaseKey = Keypair.generate().publicKey;
+    const [positionKey, positionBump] = await pda(["market_position", marketKey, positionBaseKey]);
+    await program.rpc.marketDeposit(positionBump, positionBaseKey, bn(10, 6), bn(2, 9), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        marketConfig: marketConfigKey,
+        market: marketKey,
+        position: positionKey,
+        mintPayout: mintPayout.publicKey,
+        tokenPayout: tokenPayoutKey,
+        tokenQuoteUser: tokenUsdcUser,
+        tokenQuoteIssuer: tokenUsdcUser,
+        tokenQuoteTreasury: tokenUsdcTreasury,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    const positionData = await program.account.marketPosition.fetch(positionKey);
+    expect(positionData.payout.gt(bn(0))).to.be.true;
+    const marketData = await program.account.market.fetch(marketKey);
+    expect(marketData.owed).to.eqBN(positionData.payout);
+    // 1% protocol fee
+    const treasuryAfter = await mintUsdc.getAccountInfo(tokenUsdcTreasury);
+    expect(treasuryAfter.amount.sub(treasuryBefore.amount)).to.eqBN(bn(1, 5));
+  });
+
+  it('bondTwap', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    await program.rpc.bondObservationsInitialize(observationsBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        bond: bondKey,
+        observations: observationsKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    const accounts = { treasury: treasuryKey, bond: bondKey, observations: observationsKey };
+    await program.rpc.bondObserve({ accounts });
+    let data = await program.account.bondObservations.fetch(observationsKey);
+    const first = data.observations[0];
+    // within BOND_OBSERVATION_INTERVAL, the latest is updated in place
+    await new Promise(resolve => setTimeout(resolve, 2000));
+    await program.rpc.bondObserve({ accounts });
+    data = await program.account.bondObservations.fetch(observationsKey);
+    expect(data.count).to.eqBN(bn(1, 0));
+    expect(data.observations[0].time).to.eqBN(first.time);
+
+    // longer than the history
+    let error;
+    try {
+      await program.rpc.bondTwap(bn(100, 0), { accounts: { observations: observationsKey, bond: bondKey } });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('not enough bond price history for window');
+
+    // deposits record observations too
+    await new Promise(resolve => setTimeout(resolve, 31000));
+    await program.rpc.bondDeposit(bn(10, 6), bn(1000000, 9), bn(0), [], {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        bond: bondKey,
+        allowlist: allowlistKey,
+        allowance: allowanceKey,
+        schedule: scheduleKey,
+        observations: observationsKey,
+        oracle: oracleKey,
+        user: userKey,
+        mintBond: mintUsdc.publicKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenBondUser: tokenUsdcUser,
+        tokenBondTreasury: tokenUsdcTreasury,
+        tokenReserveDao: tokenReserveDao,
+        tokenReserveStaking: treasuryData.tokenReserveStaking,
+        tokenStakingVesting: treasuryData.tokenStakingVesting,
+        tokenProgram: spl.TOKEN_PROGRAM_ID,
+      }
+    });
+    data = await program.account.bondObservations.fetch(observationsKey);
+    expect(data.count).to.eqBN(bn(2, 0));
+    expect(data.index).to.eqBN(bn(1, 0));
+    const earliest = data.observations[0], latest = data.observations[1];
+    expect(latest.time.sub(earliest.time).gte(bn(30, 0))).to.be.true;
+    expect(latest.priceCumulative).to.eqBN(earliest.priceCumulative.add(earliest.price.mul(latest.time.sub(earliest.time))));
+
+    const result = await program.simulate.bondTwap(bn(2, 0), {
+      accounts: { observations: observationsKey, bond: bondKey }
+    });
+    const log = result.raw.find(l => l.startsWith(`Program return: ${programId} `));
+    const twap = new BN(Buffer.from(log.split(' ')[3], 'base64'), 'le');
+    expect(twap.gte(BN.min(earliest.price, latest.price))).to.be.true;
+    expect(twap.lte(BN.max(earliest.price, latest.price))).to.be.true;
+  });
+
+  it('oracle', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    const [feedKey, feedBump] = await pda(["price_feed", wallet.publicKey]);
+    await program.rpc.priceFeedInitialize(feedBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        feed: feedKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+    // below the bond price
+    await program.rpc.priceFeedSet(bn(1, 8), { accounts: { signer: wallet.publicKey, feed: feedKey } });
+    await program.rpc.oracleInitialize(oracleBump, bn(1, 0), bn(0), bn(60, 0), bn(0), bn(0), {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        oracle: oracleKey,
+        source: feedKey,
+        sourceQuote: feedKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      }
+    });
+
+    const update = program.instruction.oracleUpdate({
+      accounts: { oracle: oracleKey, source: feedKey, sourceQuote: feedKey }
+    });
+    const accounts = {
+      signer: wallet.publicKey,
+      treasury: treasuryKey,
+      bond: bondKey,
+      allowlist: allowlistKey,
+      allowance: allowanceKey,
+      schedule: scheduleKey,
+      observations: observationsKey,
+      oracle: oracleKey,
+      user: userKey,
+      mintBond: mintUsdc.publicKey,
+      mintReserve: mintReserve.publicKey,
+      mintStaking: mintStaking.publicKey,
+      tokenBondUser: tokenUsdcUser,
+      tokenBondTreasury: tokenUsdcTreasury,
+      tokenReserveDao: tokenReserveDao,
+      tokenReserveStaking: treasuryData.tokenReserveStaking,
+      tokenStakingVesting: treasuryData.tokenStakingVesting,
+      tokenProgram: spl.TOKEN_PROGRAM_ID,
+    };
+    let error;
+    try {
+      await program.rpc.bondDeposit(bn(10, 6), bn(1000000, 9), bn(0), [], { accounts, instructions: [update] });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('bond priced above market');
+
+    await program.rpc.priceFeedSet(bn(1000, 9), { accounts: { signer: wallet.publicKey, feed: feedKey } });
+    await program.rpc.bondDeposit(bn(10, 6), bn(1000000, 9), bn(0), [], { accounts, instructions: [update] });
+    let oracleData = await program.account.oracle.fetch(oracleKey);
+    expect(oracleData.price).to.eqBN(bn(1000, 9));
+
+    // bonding at the market price lifts backing per token above 1, then
+    // bonds priced at 1 are below it
+    const configureAccounts = { signer: wallet.publicKey, treasury: treasuryKey, role: roleKey, bond: bondKey };
+    const configure = (minPrice) => program.rpc.bondConfigure(
+      bn(3600, 0), bn(1, 9), minPrice, bn(10, 9), bn(100000, 9), bn(5000, 0), bn(500, 0),
+      { accounts: configureAccounts }
+    );
+    await configure(bn(1000, 9));
+    const reserves = (await program.account.treasury.fetch(treasuryKey)).totalReserves;
+    const supply = (await mintReserve.getMintInfo()).supply;
+    const value = (supply.gt(reserves) ? supply.sub(reserves).muln(2) : bn(0)).add(bn(20));
+    const amount = value.divn(1000);
+    await mintUsdc.mintTo(tokenUsdcUser, mintUsdcAuthority, [], amount.toString());
+    await program.rpc.bondDeposit(amount, bn(1000000, 9), bn(0), [], { accounts, instructions: [update] });
+    await configure(bn(1, 9));
+    error = undefined;
+    try {
+      await program.rpc.bondDeposit(bn(10, 6), bn(1000000, 9), bn(0), [], { accounts, instructions: [update] });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('bond priced below backing');
+
+    // a pool price only counts once it held for a window, the pool's quote
+    // side is the wallet's here so it can be moved and restored
+    const tokenUsdcPool = await mintUsdc.createAccount(wallet.publicKey);
+    await mintUsdc.mintTo(tokenUsdcPool, mintUsdcAuthority, [], bn(1000, 6).toString());
+    const poolAccounts = {
+      signer: wallet.publicKey,
+      treasury: treasuryKey,
+      oracle: oracleKey,
+      source: treasuryData.tokenReserveStaking,
+      sourceQuote: tokenUsdcPool,
+    };
+    await program.rpc.oracleConfigure(bn(0), bn(6, 0), bn(60, 0), bn(2, 0), bn(2, 0), { accounts: poolAccounts });
+    const poolUpdate = () => program.instruction.oracleUpdate({
+      accounts: { oracle: oracleKey, source: treasuryData.tokenReserveStaking, sourceQuote: tokenUsdcPool }
+    });
+    await program.provider.send(new anchor.web3.Transaction().add(poolUpdate()));
+    error = undefined;
+    try {
+      await program.rpc.bondDeposit(bn(10, 6), bn(1000000, 9), bn(0), [], { accounts });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('oracle price stale');
+
+    const spot = async () => {
+      const quote = (await mintUsdc.getAccountInfo(tokenUsdcPool)).amount;
+      const reserve = (await mintReserve.getAccountInfo(treasuryData.tokenReserveStaking)).amount;
+      return quote.mul(bn(1)).div(bn(1, 6)).mul(bn(1)).div(reserve);
+    };
+    const price = await spot();
+    await new Promise(resolve => setTimeout(resolve, 3000));
+    await program.provider.send(new anchor.web3.Transaction().add(poolUpdate()));
+    oracleData = await program.account.oracle.fetch(oracleKey);
+    expect(oracleData.price).to.eqBN(price);
+    expect(oracleData.spot).to.eqBN(price);
+
+    // moving the pool around an update in one transaction doesn't get it
+    // read, it was read less than an interval ago
+    const spotUpdated = oracleData.spotUpdated;
+    const moved = bn(100000, 6).toString();
+    await mintUsdc.mintTo(tokenUsdcUser, mintUsdcAuthority, [], moved);
+    await program.provider.send(new anchor.web3.Transaction().add(
+      spl.Token.createTransferInstruction(spl.TOKEN_PROGRAM_ID, tokenUsdcUser, tokenUsdcPool, wallet.publicKey, [], moved),
+      poolUpdate(),
+      spl.Token.createTransferInstruction(spl.TOKEN_PROGRAM_ID, tokenUsdcPool, tokenUsdcUser, wallet.publicKey, [], moved),
+    ));
+    oracleData = await program.account.oracle.fetch(oracleKey);
+    expect(oracleData.spot).to.eqBN(price);
+    expect(oracleData.spotUpdated).to.eqBN(spotUpdated);
+
+    // a donation moves the pool, not the price
+    await new Promise(resolve => setTimeout(resolve, 3000));
+    await mintUsdc.mintTo(tokenUsdcPool, mintUsdcAuthority, [], bn(1000, 6).toString());
+    await program.provider.send(new anchor.web3.Transaction().add(poolUpdate()));
+    oracleData = await program.account.oracle.fetch(oracleKey);
+    expect(oracleData.price).to.eqBN(price);
+    expect(oracleData.spot).to.eqBN(await spot());
+    expect(oracleData.spot.gt(price)).to.be.true;
+  });
+
+  it('protocolMetrics', async () => {
+    const treasuryData = await program.account.treasury.fetch(treasuryKey);
+    const epochData = await program.account.epoch.fetch(epochKey);
+    const reserveSupply = (await mintReserve.getMintInfo()).supply;
+    const stakingSupply = (await mintStaking.getMintInfo()).supply;
+    const staked = (await mintReserve.getAccountInfo(treasuryData.tokenReserveStaking)).amount;
+    const result = await program.simulate.protocolMetrics({
+      accounts: {
+        treasury: treasuryKey,
+        epoch: epochKey,
+        mintReserve: mintReserve.publicKey,
+        mintStaking: mintStaking.publicKey,
+        tokenReserveStaking: treasuryData.tokenReserveStaking,
+      }
+    });
+    const log = result.raw.find(l => l.startsWith(`Program return: ${programId} `));
+    const data = Buffer.from(log.split(' ')[3], 'base64');
+    const [backingPerToken, stakingIndex, rewardRate, roiFiveDay, apy, runwayDays] =
+      [0, 1, 2, 3, 4, 5].map(i => new BN(data.slice(i * 8, i * 8 + 8), 'le'));
+
+    const one = bn(1);
+    const pool = staked.sub(treasuryData.stakingWarmupTotal).sub(treasuryData.stakingCooldownTotal);
+    expect(backingPerToken).to.eqBN(treasuryData.totalReserves.mul(one).div(reserveSupply));
+    expect(stakingIndex).to.eqBN(pool.mul(one).div(stakingSupply));
+    expect(rewardRate).to.eqBN(epochData.distribute.mul(one).div(pool));
+
+    const rate = rewardRate.toNumber() / 1e9;
+    const length = epochData.length.toNumber();
+    const close = (actual, expected) =>
+      expect(Math.abs(actual - expected)).to.be.lte(Math.abs(expected) * 1e-4 + 1e-9);
+    close(roiFiveDay.toNumber() / 1e9, Math.pow(1 + rate, Math.floor(5 * 86400 / length)) - 1);
+    close(apy.toNumber() / 1e9, Math.pow(1 + rate, Math.floor(365 * 86400 / length)) - 1);
+    if (rate == 0) {
+      expect(runwayDays.toString()).to.eq('18446744073709551615');
+    } else {
+      const reserves = treasuryData.totalReserves.toNumber();
+      const epochs = reserves < pool.toNumber() ? 0 :
+        Math.floor(Math.log(reserves / pool.toNumber()) / Math.log(1 + rate));
+      const expected = Math.floor(epochs * length / 86400);
+      expect(Math.abs(runwayDays.toNumber() - expected)).to.be.lte(expected * 1e-3 + 1);
+    }
+  });
+
+
+  it ('userClose', async () => {
+    let error;
+    try {
+      await program.rpc.userClose({ accounts: { signer: wallet.publicKey, user: userKey } });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('user has bonds or stakes pending');
+
+    const other = Keypair.generate();
+    await program.provider.connection.confirmTransaction(
+      await program.provider.connection.requestAirdrop(other.publicKey, 1000000000)
+    );
+    const [otherUserKey, otherUserBump] = await pda(["user", treasuryKey, other.publicKey]);
+    await program.rpc.userInitialize(otherUserBump, {
+      accounts: {
+        signer: other.publicKey,
+        treasury: treasuryKey,
+        user: otherUserKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      },
+      signers: [other],
+    });
+    await program.rpc.userClose({
+      accounts: { signer: other.publicKey, user: otherUserKey },
+      signers: [other],
+    });
+    expect(await program.provider.connection.getAccountInfo(otherUserKey)).to.be.null;
+
+    // and can be initialized again
+    await program.rpc.userInitialize(otherUserBump, {
+      accounts: {
+        signer: other.publicKey,
+        treasury: treasuryKey,
+        user: otherUserKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      },
+      signers: [other],
+    });
+    const otherUserData = await program.account.user.fetch(otherUserKey);
+    expect(otherUserData.signer).to.eqPubkey(other.publicKey);
+    expect(otherUserData.version).to.eq(1);
+  });
+
+  it ('migrate', async () => {
+    expect((await program.account.treasury.fetch(treasuryKey)).version).to.eq(1);
+    expect((await program.account.user.fetch(userKey)).version).to.eq(1);
+
+    let error;
+    try {
+      await program.rpc.migrateUser({ accounts: { signer: wallet.publicKey, user: userKey } });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('unsupported account version, migrate it first');
+
+    error = undefined;
+    const [migrationKey, migrationBump] = await pda(["migration", treasuryKey]);
+    try {
+      await program.rpc.migrateTreasuryBegin(migrationBump, {
+        accounts: {
+          signer: wallet.publicKey,
+          treasury: treasuryKey,
+          migration: migrationKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+      });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('unsupported account version, migrate it first');
+    expect(await program.provider.connection.getAccountInfo(migrationKey)).to.be.null;
+  });
+
+  it ('migrateBond', async () => {
+    // loaded from tests/fixtures/bond_v0.json
+    const mintOldKey = new PublicKey(Buffer.alloc(32, 2));
+    const [bondOldKey, bondOldBump] = await pda(["bond", treasuryKey, mintOldKey]);
+    const [migrationKey, migrationBump] = await pda(["migration", bondOldKey]);
+    const before = await program.provider.connection.getAccountInfo(bondOldKey);
+    expect(before.data.length).to.be.lt(8 + program.account.bond.size);
+
+    await program.rpc.migrateBondBegin(migrationBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        bond: bondOldKey,
+        migration: migrationKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      },
+    });
+    expect(await program.provider.connection.getAccountInfo(bondOldKey)).to.be.null;
+
+    // the closed bond can't be created anew while its data is in the buffer
+    let error;
+    try {
+      await program.rpc.bondInitialize(bondOldBump, {
+        accounts: {
+          signer: wallet.publicKey,
+          treasury: treasuryKey,
+          role: roleKey,
+          bond: bondOldKey,
+          mintBond: mintOldKey,
+          migration: migrationKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        }
+      });
+    } catch (e) {
+      error = e;
+    }
+    expect(error.msg).to.eq('unsupported account version, migrate it first');
+
+    await program.rpc.migrateBondFinish(bondOldBump, {
+      accounts: {
+        signer: wallet.publicKey,
+        treasury: treasuryKey,
+        bond: bondOldKey,
+        mintBond: mintOldKey,
+        migration: migrationKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      },
+    });
+    expect(await program.provider.connection.getAccountInfo(migrationKey)).to.be.null;
+    const bondData = await program.account.bond.fetch(bondOldKey);
+    expect(bondData.version).to.eq(1);
+    expect(bondData.bump).to.eq(bondOldBump);
+    expect(bondData.treasury).to.eqPubkey(treasuryKey);
+    expect(bondData.mintBond).to.eqPubkey(mintOldKey);
+    expect(bondData.tokenDecimals).to.eq(6);
+    expect(bondData.vestingPeriod).to.eqBN(bn(3600, 0));
+    expect(bondData.rfvRate).to.eqBN(bn(1, 9));
+    expect(bondData.minPrice).to.eqBN(bn(1, 9));
+    expect(bondData.maxPayout).to.eqBN(bn(10, 9));
+    expect(bondData.maxDebt).to.eqBN(bn(1000, 9));
+    expect(bondData.fee).to.eqBN(bn(5000, 0));
+    expect(bondData.bcv).to.eqBN(bn(500, 0));
+    expect(bondData.totalDebt).to.eqBN(bn(7, 9));
+    expect(bondData.totalDebtLast).to.eqBN(bn(1600000000, 0));
+    expect(bondData.totalDebtAlltime).to.eqBN(bn(123, 9));
+    expect(bondData.gaugeEpoch).to.eqBN(bn(3, 0));
+    expect(bondData.gaugeVotes).to.eqBN(bn(11, 9));
+    expect(bondData.gaugeVotesLast).to.eqBN(bn(13, 9));
+    expect(bondData.paused).to.eqBN(bn(1600000100, 0));
+    expect(bondData.maxDebtUser).to.eqBN(bn(50, 9));
+    expect(bondData.maxPayoutSlot).to.eqBN(bn(2, 9));
+    expect(bondData.slot).to.eqBN(bn(42, 0));
+    expect(bondData.slotPayout).to.eqBN(bn(1, 9));
+    expect(bondData.exitPenalty).to.eqBN(bn(0));
+  });
+});
+
+function sha256(...buffers) {
+  const hash = createHash('sha256');
+  buffers.forEach(b => hash.update(b));
+  return hash.digest();
+}
 
 function bn(value, decimals = 9) {
   return new BN(value).mul(new BN(10).pow(new BN(decimals)));